@@ -0,0 +1,6 @@
+ALTER TABLE songs
+    ADD COLUMN key              TEXT,
+    ADD COLUMN bpm              INTEGER,
+    ADD COLUMN time_signature   TEXT,
+    ADD COLUMN capo             INTEGER,
+    ADD COLUMN duration_seconds INTEGER;
@@ -0,0 +1,8 @@
+CREATE TABLE download_tokens
+(
+    token      TEXT PRIMARY KEY,
+    user_id    INTEGER     NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX download_tokens_user_id ON download_tokens (user_id);
@@ -0,0 +1,6 @@
+-- Setlists made before there were accounts have no owner. Everybody can see them, but nobody can
+-- change them until they are given an owner with the `assign-owner` command.
+ALTER TABLE setlists
+    ADD COLUMN owner_id INTEGER REFERENCES users (id) ON DELETE CASCADE;
+
+CREATE INDEX setlists_owner_id ON setlists (owner_id);
@@ -0,0 +1,10 @@
+-- V6 says songs without an owner stay shared until someone claims them, but nobody can claim a
+-- song: they keep their rights until they are given an owner with the `assign-owner` command.
+-- Applied migrations cannot be edited, as their checksums would no longer match, so the rules
+-- are documented here, on the objects themselves.
+COMMENT ON COLUMN songs.owner_id IS
+    'NULL for songs written before there were accounts, until the assign-owner command gives them an owner.';
+
+COMMENT ON FUNCTION song_role(INTEGER, INTEGER) IS
+    'What a user may do with a song: owner, editor, viewer, or NULL for no access. Songs without an '
+    'owner can be read by anonymous users and edited by every logged-in user.';
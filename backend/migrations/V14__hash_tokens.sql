@@ -0,0 +1,11 @@
+-- Session and download tokens are stored as their SHA-256, so that reading the database is not
+-- enough to act as someone else. Tokens handed out before stay valid.
+ALTER TABLE sessions
+    RENAME COLUMN token TO token_hash;
+UPDATE sessions
+SET token_hash = encode(sha256(convert_to(token_hash, 'UTF8')), 'hex');
+
+ALTER TABLE download_tokens
+    RENAME COLUMN token TO token_hash;
+UPDATE download_tokens
+SET token_hash = encode(sha256(convert_to(token_hash, 'UTF8')), 'hex');
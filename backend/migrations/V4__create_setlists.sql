@@ -0,0 +1,17 @@
+CREATE TABLE setlists
+(
+    id   SERIAL PRIMARY KEY,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE setlist_entries
+(
+    id           SERIAL PRIMARY KEY,
+    setlist_id   INTEGER NOT NULL REFERENCES setlists (id) ON DELETE CASCADE,
+    song_id      INTEGER NOT NULL REFERENCES songs (id) ON DELETE CASCADE,
+    position     INTEGER NOT NULL,
+    key_override TEXT,
+    notes        TEXT    NOT NULL DEFAULT ''
+);
+
+CREATE INDEX setlist_entries_setlist_id ON setlist_entries (setlist_id, position);
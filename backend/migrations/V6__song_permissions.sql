@@ -0,0 +1,34 @@
+-- Songs written before there were accounts have no owner. They stay shared with everybody until
+-- someone claims them.
+ALTER TABLE songs
+    ADD COLUMN owner_id INTEGER REFERENCES users (id) ON DELETE SET NULL;
+
+CREATE TABLE song_permissions
+(
+    song_id INTEGER NOT NULL REFERENCES songs (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role    TEXT    NOT NULL CHECK (role IN ('viewer', 'editor')),
+    PRIMARY KEY (song_id, user_id)
+);
+
+CREATE INDEX song_permissions_user_id ON song_permissions (user_id);
+
+-- What a user may do with a song: 'owner', 'editor', 'viewer', or NULL for no access. Anonymous
+-- users are passed as NULL.
+CREATE FUNCTION song_role(p_song_id INTEGER, p_user_id INTEGER) RETURNS TEXT
+    LANGUAGE sql
+    STABLE
+AS
+$$
+SELECT CASE
+           WHEN songs.owner_id IS NULL AND p_user_id IS NULL THEN 'viewer'
+           WHEN songs.owner_id IS NULL THEN 'editor'
+           WHEN songs.owner_id = p_user_id THEN 'owner'
+           ELSE (SELECT role
+                 FROM song_permissions
+                 WHERE song_permissions.song_id = songs.id
+                   AND song_permissions.user_id = p_user_id)
+           END
+FROM songs
+WHERE songs.id = p_song_id
+$$;
@@ -0,0 +1,16 @@
+CREATE TABLE song_revisions
+(
+    id         SERIAL PRIMARY KEY,
+    song_id    INTEGER     NOT NULL REFERENCES songs (id) ON DELETE CASCADE,
+    content    TEXT        NOT NULL,
+    author_id  INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX song_revisions_song_id ON song_revisions (song_id, id);
+
+-- Start the history of existing songs with their current content.
+INSERT INTO song_revisions (song_id, content, author_id)
+SELECT id, content, owner_id
+FROM songs
+WHERE content <> '';
@@ -0,0 +1,14 @@
+-- The lyrics without chords and markup, kept up to date by the application. NULL for songs that
+-- have not been indexed yet; the server fills these in when it starts.
+ALTER TABLE songs
+    ADD COLUMN lyrics TEXT;
+
+ALTER TABLE songs
+    ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', coalesce(title, '')), 'A') ||
+        setweight(to_tsvector('simple', coalesce(artist, '')), 'B') ||
+        setweight(to_tsvector('simple', coalesce(lyrics, '')), 'C')
+        ) STORED;
+
+CREATE INDEX songs_search_vector ON songs USING GIN (search_vector);
+CREATE INDEX songs_artist ON songs (lower(artist));
@@ -0,0 +1,17 @@
+CREATE TABLE tags
+(
+    id   SERIAL PRIMARY KEY,
+    name TEXT NOT NULL
+);
+
+-- "Wedding" and "wedding" are the same tag.
+CREATE UNIQUE INDEX tags_name ON tags (lower(name));
+
+CREATE TABLE song_tags
+(
+    song_id INTEGER NOT NULL REFERENCES songs (id) ON DELETE CASCADE,
+    tag_id  INTEGER NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    PRIMARY KEY (song_id, tag_id)
+);
+
+CREATE INDEX song_tags_tag_id ON song_tags (tag_id);
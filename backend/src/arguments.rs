@@ -58,3 +58,15 @@ pub struct ChordmateArgs {
     #[arg(long, value_enum, default_value = "info")]
     pub log_level: LevelFilter,
 }
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct AssignOwnerArgs {
+    #[command(flatten)]
+    pub db: DatabaseArgs,
+    #[arg(
+        long,
+        help = "The user who becomes the owner of everything that has no owner yet."
+    )]
+    pub username: String,
+}
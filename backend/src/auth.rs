@@ -0,0 +1,291 @@
+//! User accounts, password hashing and the sessions that authenticate requests.
+
+use crate::database_connection::DatabaseConnection;
+use crate::error::{ChordmateError, ChordmateResult};
+use crate::song_repository::SongRepository;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::extract::Request;
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Extension;
+use juniper::GraphQLObject;
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, LazyLock};
+use tokio_postgres::Row;
+
+/// How long a session stays valid after logging in.
+pub const SESSION_DAYS: i32 = 30;
+
+/// How long a download token can be used after it was created.
+pub const DOWNLOAD_TOKEN_MINUTES: i32 = 5;
+
+#[derive(GraphQLObject, Clone, Debug)]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+}
+
+impl User {
+    pub fn from_row(row: &Row) -> Result<Self, tokio_postgres::Error> {
+        Ok(User {
+            id: row.try_get("id")?,
+            username: row.try_get("username")?,
+        })
+    }
+}
+
+/// Who is making a request, passed to every resolver.
+#[derive(Clone)]
+pub struct Context {
+    /// Lets the fields of objects such as songs load what belongs to them.
+    pub database_connection: DatabaseConnection,
+    pub songs: Arc<dyn SongRepository>,
+    pub user: Option<User>,
+    /// The session token the request was made with, if it belongs to a valid session.
+    pub session_token: Option<String>,
+}
+
+impl juniper::Context for Context {}
+
+impl Context {
+    pub fn anonymous(
+        database_connection: DatabaseConnection,
+        songs: Arc<dyn SongRepository>,
+    ) -> Self {
+        Context {
+            database_connection,
+            songs,
+            user: None,
+            session_token: None,
+        }
+    }
+
+    pub fn user_id(&self) -> Option<i32> {
+        self.user.as_ref().map(|user| user.id)
+    }
+
+    /// The user making the request, or an error for anonymous requests.
+    pub fn user(&self) -> ChordmateResult<&User> {
+        self.user.as_ref().ok_or_else(|| {
+            ChordmateError::Unauthenticated(String::from("You need to be logged in."))
+        })
+    }
+}
+
+/// Checked instead of a user's hash when there is no user with the name someone logs in with, so
+/// that unknown usernames cannot be told apart by how quickly the login fails.
+static UNKNOWN_USER_HASH: LazyLock<String> = LazyLock::new(|| {
+    hash(&new_session_token()).expect("Hashing a random password should not fail.")
+});
+
+fn hash(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+fn verify(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+/// Argon2 is slow on purpose, so hashing runs on a blocking thread rather than holding up the
+/// other requests.
+pub async fn hash_password(password: String) -> ChordmateResult<String> {
+    Ok(tokio::task::spawn_blocking(move || hash(&password)).await??)
+}
+
+/// Checks a password against the hash of a user, or `None` if there is no such user. Takes as
+/// long either way.
+pub async fn verify_password(
+    password: String,
+    password_hash: Option<String>,
+) -> ChordmateResult<bool> {
+    Ok(tokio::task::spawn_blocking(move || {
+        // Made on the first login of anybody, not only of unknown users, as that takes time too.
+        let unknown_user_hash = LazyLock::force(&UNKNOWN_USER_HASH);
+        match password_hash {
+            Some(password_hash) => verify(&password, &password_hash),
+            None => {
+                verify(&password, unknown_user_hash);
+                false
+            }
+        }
+    })
+    .await?)
+}
+
+pub fn new_session_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex(&bytes)
+}
+
+/// What is stored of a session or download token, so that the tokens cannot be used by anyone
+/// who gets to read the database. Tokens are random enough that a plain SHA-256 is sufficient.
+pub fn token_hash(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Looks up the user a session token belongs to. Unknown and expired tokens authenticate nobody.
+pub async fn authenticate(
+    database_connection: &DatabaseConnection,
+    songs: Arc<dyn SongRepository>,
+    token: &str,
+) -> Context {
+    let user = find_user(
+        database_connection,
+        "SELECT users.id, users.username FROM sessions \
+         JOIN users ON users.id = sessions.user_id \
+         WHERE sessions.token_hash = $1 AND sessions.expires_at > now()",
+        token,
+    )
+    .await;
+    Context {
+        database_connection: database_connection.clone(),
+        songs,
+        session_token: user.as_ref().map(|_| token.to_string()),
+        user,
+    }
+}
+
+/// Looks up the user a download token belongs to, like [`authenticate`] does for sessions. The
+/// context has no session token, so it cannot be used to log out.
+pub async fn authenticate_download(
+    database_connection: &DatabaseConnection,
+    songs: Arc<dyn SongRepository>,
+    token: &str,
+) -> Context {
+    let user = find_user(
+        database_connection,
+        "SELECT users.id, users.username FROM download_tokens \
+         JOIN users ON users.id = download_tokens.user_id \
+         WHERE download_tokens.token_hash = $1 AND download_tokens.expires_at > now()",
+        token,
+    )
+    .await;
+    Context {
+        user,
+        ..Context::anonymous(database_connection.clone(), songs)
+    }
+}
+
+/// Runs a query for the user a token belongs to. Errors are logged and authenticate nobody.
+async fn find_user(
+    database_connection: &DatabaseConnection,
+    query: &str,
+    token: &str,
+) -> Option<User> {
+    match database_connection.get().await {
+        Ok(client) => client
+            .query_opt(query, &[&token_hash(token)])
+            .await
+            .inspect_err(|error| eprintln!("Error: {error}"))
+            .ok()
+            .flatten()
+            .and_then(|row| User::from_row(&row).ok()),
+        Err(error) => {
+            eprintln!("Error: {error}");
+            None
+        }
+    }
+}
+
+/// Reads the bearer token of a request and makes the resulting [`Context`] available to the
+/// handlers as an extension.
+pub async fn auth_layer(
+    Extension(database_connection): Extension<DatabaseConnection>,
+    Extension(songs): Extension<Arc<dyn SongRepository>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+    let context = match token {
+        Some(token) => authenticate(&database_connection, songs, &token).await,
+        None => Context::anonymous(database_connection, songs),
+    };
+    request.extensions_mut().insert(context);
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn passwords_are_hashed_with_a_salt() {
+        let first = hash_password(String::from("correct horse")).await.unwrap();
+        let second = hash_password(String::from("correct horse")).await.unwrap();
+        assert!(first.starts_with("$argon2"));
+        assert!(!first.contains("correct horse"));
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn the_right_password_is_accepted() {
+        let password_hash = hash_password(String::from("correct horse")).await.unwrap();
+        assert!(
+            verify_password(String::from("correct horse"), Some(password_hash))
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn wrong_passwords_are_rejected() {
+        let password_hash = hash_password(String::from("correct horse")).await.unwrap();
+        assert!(
+            !verify_password(String::from("Correct horse"), Some(password_hash))
+                .await
+                .unwrap()
+        );
+        assert!(!verify_password(
+            String::from("correct horse"),
+            Some(String::from("not a hash"))
+        )
+        .await
+        .unwrap());
+    }
+
+    #[tokio::test]
+    async fn unknown_users_are_rejected() {
+        assert!(!verify_password(String::from("correct horse"), None)
+            .await
+            .unwrap());
+    }
+
+    #[test]
+    fn session_tokens_are_random() {
+        let token = new_session_token();
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, new_session_token());
+    }
+
+    #[test]
+    fn tokens_are_stored_as_their_sha256() {
+        assert_eq!(
+            token_hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let token = new_session_token();
+        assert_eq!(token_hash(&token), token_hash(&token));
+        assert_ne!(token_hash(&token), token);
+    }
+}
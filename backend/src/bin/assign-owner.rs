@@ -0,0 +1,51 @@
+//! Gives the songs and setlists made before there were accounts an owner, once the accounts are
+//! set up.
+
+use chordmate::arguments::AssignOwnerArgs;
+use clap::Parser;
+use tokio_postgres::NoTls;
+
+#[tokio::main]
+async fn main() {
+    let args = AssignOwnerArgs::parse();
+    let (mut client, connection) = args.db.config().connect(NoTls).await.unwrap();
+
+    // Drive the connection on a background task
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let transaction = client.transaction().await.unwrap();
+    let Some(user) = transaction
+        .query_opt(
+            "SELECT id FROM users WHERE username = $1;",
+            &[&args.username],
+        )
+        .await
+        .unwrap()
+    else {
+        eprintln!("There is no user named '{}'.", args.username);
+        std::process::exit(1);
+    };
+    let user_id: i32 = user.get(0);
+    let songs = transaction
+        .execute(
+            "UPDATE songs SET owner_id = $1 WHERE owner_id IS NULL;",
+            &[&user_id],
+        )
+        .await
+        .unwrap();
+    let setlists = transaction
+        .execute(
+            "UPDATE setlists SET owner_id = $1 WHERE owner_id IS NULL;",
+            &[&user_id],
+        )
+        .await
+        .unwrap();
+    transaction.commit().await.unwrap();
+    println!(
+        "{songs} songs and {setlists} setlists now belong to {}.",
+        args.username
+    );
+}
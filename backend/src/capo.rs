@@ -0,0 +1,90 @@
+//! Finding the capo position that lets guitarists play a song with the easiest chord shapes.
+
+use crate::chord::{Chord, Key};
+use crate::chord_diagram::open_guitar_shape;
+use crate::song_content::ParsedSong;
+use juniper::GraphQLObject;
+
+/// Guitars become awkward to play with the capo any higher than this.
+pub const MAX_CAPO: i32 = 12;
+
+#[derive(GraphQLObject, Clone, Debug, PartialEq)]
+pub struct CapoSuggestion {
+    /// The fret to put the capo on, `0` for no capo.
+    pub capo: i32,
+    /// The chords to finger with the capo on, in the order they first appear in the song.
+    pub shapes: Vec<String>,
+    /// How many of the shapes can be played in the open position.
+    pub open_shapes: i32,
+    /// How many of the chords played in the song can be played in the open position, counting
+    /// every time a chord is played.
+    pub open_chords_played: i32,
+}
+
+/// Every capo position up to `max_fret`, the one with the most open shapes first.
+///
+/// The shapes are spelled as is conventional for the key the song sounds in after taking off the
+/// capo, if `key` is given.
+pub fn suggestions(song: &ParsedSong, max_fret: u8, key: Option<Key>) -> Vec<CapoSuggestion> {
+    let played: Vec<Chord> = song.chords().filter_map(|a| a.chord()).collect();
+    let mut distinct: Vec<&Chord> = Vec::new();
+    for chord in &played {
+        if !distinct.contains(&chord) {
+            distinct.push(chord);
+        }
+    }
+
+    let mut suggestions: Vec<CapoSuggestion> = (0..=max_fret)
+        .map(|capo| {
+            let semitones = -(capo as i32);
+            let prefer_flats = key.is_some_and(|key| key.transpose(semitones).prefers_flats());
+            let shape = |chord: &Chord| chord.transpose(semitones, prefer_flats);
+            let is_open = |chord: &Chord| open_guitar_shape(&shape(chord)).is_some();
+            CapoSuggestion {
+                capo: capo as i32,
+                shapes: distinct.iter().map(|c| shape(c).to_string()).collect(),
+                open_shapes: distinct.iter().filter(|c| is_open(c)).count() as i32,
+                open_chords_played: played.iter().filter(|c| is_open(c)).count() as i32,
+            }
+        })
+        .collect();
+    // Sorting is stable, so lower capo positions come first among equally good ones.
+    suggestions.sort_by_key(|s| (-s.open_shapes, -s.open_chords_played));
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_capo_that_allows_open_shapes_comes_first() {
+        let song = ParsedSong::from_text("Bb  Gm  Eb  F\nla la la la\nBb\nla");
+        let best = &suggestions(&song, 7, None)[0];
+        assert_eq!(best.capo, 3);
+        assert_eq!(best.shapes, ["G", "Em", "C", "D"]);
+        assert_eq!(best.open_shapes, 4);
+        assert_eq!(best.open_chords_played, 5);
+    }
+
+    #[test]
+    fn every_position_is_suggested_and_ties_keep_the_lower_capo() {
+        let song = ParsedSong::from_text("G  C  D\nla la la");
+        let all = suggestions(&song, 5, None);
+        assert_eq!(all.len(), 6);
+        // With the capo on the fifth fret, the shapes D, G and A are all open too.
+        assert_eq!((all[0].capo, all[1].capo), (0, 5));
+    }
+
+    #[test]
+    fn shapes_are_spelled_for_the_key_of_the_song() {
+        let song = ParsedSong::from_text("E  B\nla la");
+        let sharps = suggestions(&song, 1, None);
+        let flats = suggestions(&song, 1, Key::parse("E"));
+        let shapes_at = |all: &[CapoSuggestion], capo| {
+            all.iter().find(|s| s.capo == capo).unwrap().shapes.clone()
+        };
+        assert_eq!(shapes_at(&sharps, 1), ["D#", "A#"]);
+        assert_eq!(shapes_at(&flats, 1), ["Eb", "Bb"]);
+    }
+}
@@ -0,0 +1,552 @@
+use juniper::{graphql_object, GraphQLEnum};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Note {
+    pub letter: char,
+    /// Number of semitones the letter is raised (positive) or lowered (negative).
+    pub accidental: i8,
+}
+
+impl Note {
+    pub fn parse(s: &str) -> Option<(Note, &str)> {
+        let mut chars = s.chars();
+        let letter = chars.next().filter(|c| ('A'..='G').contains(c))?;
+        let rest = chars.as_str();
+        let (accidental, rest) = if let Some(rest) = rest.strip_prefix(['#', '♯']) {
+            (1, rest)
+        } else if let Some(rest) = rest.strip_prefix(['b', '♭']) {
+            (-1, rest)
+        } else {
+            (0, rest)
+        };
+        Some((Note { letter, accidental }, rest))
+    }
+
+    /// Spells the pitch class `semitone` (`0` being C) with a sharp or a flat where needed.
+    pub fn from_semitone(semitone: u8, prefer_flats: bool) -> Note {
+        let (letter, accidental) = match (semitone % 12, prefer_flats) {
+            (0, _) => ('C', 0),
+            (1, false) => ('C', 1),
+            (1, true) => ('D', -1),
+            (2, _) => ('D', 0),
+            (3, false) => ('D', 1),
+            (3, true) => ('E', -1),
+            (4, _) => ('E', 0),
+            (5, _) => ('F', 0),
+            (6, false) => ('F', 1),
+            (6, true) => ('G', -1),
+            (7, _) => ('G', 0),
+            (8, false) => ('G', 1),
+            (8, true) => ('A', -1),
+            (9, _) => ('A', 0),
+            (10, false) => ('A', 1),
+            (10, true) => ('B', -1),
+            _ => ('B', 0),
+        };
+        Note { letter, accidental }
+    }
+
+    pub fn transpose(&self, semitones: i32, prefer_flats: bool) -> Note {
+        let semitone = ((self.semitone() as i32 + semitones.rem_euclid(12)) % 12) as u8;
+        Note::from_semitone(semitone, prefer_flats)
+    }
+
+    /// The pitch class of this note, `0` being C.
+    pub fn semitone(&self) -> u8 {
+        let natural: i8 = match self.letter {
+            'C' => 0,
+            'D' => 2,
+            'E' => 4,
+            'F' => 5,
+            'G' => 7,
+            'A' => 9,
+            'B' => 11,
+            _ => unreachable!("Note letter must be A-G."),
+        };
+        (natural + self.accidental).rem_euclid(12) as u8
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.letter)?;
+        match self.accidental {
+            1 => write!(f, "#"),
+            -1 => write!(f, "b"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[graphql(name = "ChordQuality")]
+pub enum Quality {
+    Major,
+    Minor,
+    Diminished,
+    HalfDiminished,
+    Augmented,
+}
+
+/// A chord symbol like `Cmaj7/G` or `F#m7b5`.
+///
+/// The suffix is kept as written so that a chord can be printed exactly as the author spelled it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub root: Note,
+    pub suffix: String,
+    pub bass: Option<Note>,
+}
+
+impl Chord {
+    pub fn parse(symbol: &str) -> Option<Chord> {
+        let (root, rest) = Note::parse(symbol)?;
+        let (suffix, bass) = match rest.rfind('/') {
+            Some(i) => match Note::parse(&rest[i + 1..]) {
+                Some((bass, "")) => (&rest[..i], Some(bass)),
+                _ => (rest, None),
+            },
+            None => (rest, None),
+        };
+        parse_suffix(suffix)?;
+        Some(Chord {
+            root,
+            suffix: suffix.to_string(),
+            bass,
+        })
+    }
+
+    pub fn transpose(&self, semitones: i32, prefer_flats: bool) -> Chord {
+        Chord {
+            root: self.root.transpose(semitones, prefer_flats),
+            suffix: self.suffix.clone(),
+            bass: self
+                .bass
+                .map(|bass| bass.transpose(semitones, prefer_flats)),
+        }
+    }
+
+    /// The pitch classes of the notes in this chord, starting with the bass note.
+    pub fn pitch_classes(&self) -> Vec<u8> {
+        let (quality, extensions) = parse_suffix(&self.suffix).unwrap_or((Quality::Major, vec![]));
+        let (mut third, mut fifth, mut seventh) = match quality {
+            Quality::Major => (Some(4), Some(7), None),
+            Quality::Minor => (Some(3), Some(7), None),
+            Quality::Diminished => (Some(3), Some(6), None),
+            Quality::HalfDiminished => (Some(3), Some(6), Some(10)),
+            Quality::Augmented => (Some(4), Some(8), None),
+        };
+        let mut tensions = Vec::new();
+        for extension in &extensions {
+            let dominant = if quality == Quality::Diminished {
+                9
+            } else {
+                10
+            };
+            match extension.as_str() {
+                "5" => third = None,
+                "6" => tensions.push(9),
+                "7" => seventh = Some(dominant),
+                "9" | "11" | "13" => {
+                    seventh.get_or_insert(dominant);
+                    tensions.extend(match extension.as_str() {
+                        "9" => &[2][..],
+                        "11" => &[2, 5],
+                        _ => &[2, 9],
+                    });
+                }
+                "maj7" => seventh = Some(11),
+                "maj9" | "maj11" | "maj13" => {
+                    seventh = Some(11);
+                    tensions.extend(match extension.as_str() {
+                        "maj9" => &[2][..],
+                        "maj11" => &[2, 5],
+                        _ => &[2, 9],
+                    });
+                }
+                "2" | "sus2" => third = Some(2),
+                "4" | "sus4" => third = Some(5),
+                "b5" => fifth = Some(6),
+                "#5" => fifth = Some(8),
+                "alt" => {
+                    seventh.get_or_insert(dominant);
+                    tensions.extend([1, 3, 6, 8]);
+                }
+                other => {
+                    let degree = other.trim_start_matches("add");
+                    let (alteration, degree) = match degree.strip_prefix(['b', '#']) {
+                        Some(rest) => (if degree.starts_with('b') { -1 } else { 1 }, rest),
+                        None => (0, degree),
+                    };
+                    let interval: i8 = match degree {
+                        "2" | "9" => 2,
+                        "4" | "11" => 5,
+                        "6" | "13" => 9,
+                        _ => continue,
+                    };
+                    tensions.push((interval + alteration).rem_euclid(12) as u8);
+                }
+            }
+        }
+        let root = self.root.semitone();
+        let mut pitch_classes: Vec<u8> = self.bass.iter().map(Note::semitone).collect();
+        for interval in [Some(0), third, fifth, seventh]
+            .into_iter()
+            .flatten()
+            .chain(tensions)
+        {
+            let pitch_class = (root + interval) % 12;
+            if !pitch_classes.contains(&pitch_class) {
+                pitch_classes.push(pitch_class);
+            }
+        }
+        pitch_classes
+    }
+
+    pub fn quality(&self) -> Quality {
+        parse_suffix(&self.suffix)
+            .map(|(quality, _)| quality)
+            .unwrap_or(Quality::Major)
+    }
+
+    /// The chord as a Nashville number relative to `key`, with the suffix and bass note kept,
+    /// e.g. `6m⁷` for `Em7` or `4/5` for `C/D` in G. The numbers of the suffix are written as
+    /// superscripts, so `E7` in A minor is `5⁷` rather than a `57` that reads like a degree.
+    pub fn nashville(&self, key: &Key) -> String {
+        let suffix: String = self.suffix.chars().map(superscript).collect();
+        let mut number = format!("{}{suffix}", key.degree(&self.root));
+        if let Some(bass) = &self.bass {
+            number.push('/');
+            number.push_str(key.degree(bass));
+        }
+        number
+    }
+
+    /// The extensions, alterations and suspensions of this chord in the order they were written,
+    /// e.g. `["maj7"]` for `Cmaj7` or `["7", "b5"]` for `F#m7b5`.
+    pub fn extensions(&self) -> Vec<String> {
+        parse_suffix(&self.suffix)
+            .map(|(_, extensions)| extensions)
+            .unwrap_or_default()
+    }
+}
+
+#[graphql_object]
+impl Chord {
+    /// The chord as written, e.g. `Cmaj7/G`.
+    fn symbol(&self) -> String {
+        self.to_string()
+    }
+
+    fn root(&self) -> String {
+        self.root.to_string()
+    }
+
+    #[graphql(name = "quality")]
+    fn graphql_quality(&self) -> Quality {
+        self.quality()
+    }
+
+    #[graphql(name = "extensions")]
+    fn graphql_extensions(&self) -> Vec<String> {
+        self.extensions()
+    }
+
+    /// The bass note of a slash chord.
+    fn bass(&self) -> Option<String> {
+        self.bass.map(|bass| bass.to_string())
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.root, self.suffix)?;
+        if let Some(bass) = &self.bass {
+            write!(f, "/{bass}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A major or minor key, written like a chord: `G`, `F#m`, `Bbm`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub tonic: Note,
+    pub minor: bool,
+}
+
+impl Key {
+    /// Parses a key written as a plain major or minor chord, e.g. `A`, `Am` or `Amin`.
+    pub fn parse(s: &str) -> Option<Key> {
+        let chord = Chord::parse(s.trim())?;
+        if chord.bass.is_some() || !chord.extensions().is_empty() {
+            return None;
+        }
+        match chord.quality() {
+            Quality::Major => Some(Key {
+                tonic: chord.root,
+                minor: false,
+            }),
+            Quality::Minor => Some(Key {
+                tonic: chord.root,
+                minor: true,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The key with its tonic moved by `semitones`, spelled as is conventional for that key.
+    pub fn transpose(&self, semitones: i32) -> Key {
+        let tonic = ((self.tonic.semitone() as i32 + semitones.rem_euclid(12)) % 12) as u8;
+        Key {
+            tonic: Note::from_semitone(tonic, is_flat_key(tonic, self.minor)),
+            minor: self.minor,
+        }
+    }
+
+    pub fn prefers_flats(&self) -> bool {
+        is_flat_key(self.tonic.semitone(), self.minor)
+    }
+
+    /// The scale degree of `note` in this key, counted from the tonic, with notes outside the
+    /// major scale written as flattened degrees.
+    pub fn degree(&self, note: &Note) -> &'static str {
+        const DEGREES: [&str; 12] = [
+            "1", "b2", "2", "b3", "3", "4", "b5", "5", "b6", "6", "b7", "7",
+        ];
+        DEGREES[((note.semitone() + 12 - self.tonic.semitone()) % 12) as usize]
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.tonic, if self.minor { "m" } else { "" })
+    }
+}
+
+/// Whether the key with the given tonic is conventionally written with flats rather than sharps.
+pub fn is_flat_key(tonic: u8, minor: bool) -> bool {
+    let major_tonic = if minor { (tonic + 3) % 12 } else { tonic % 12 };
+    // F, Bb, Eb, Ab, Db and Gb major, and their relative minor keys.
+    matches!(major_tonic, 5 | 10 | 3 | 8 | 1 | 6)
+}
+
+/// The superscript form of a digit, other characters as they are.
+fn superscript(c: char) -> char {
+    match c {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        _ => c,
+    }
+}
+
+fn parse_degree(s: &str) -> Option<(&str, &str)> {
+    ["13", "11", "9", "7", "6", "5", "4", "2"]
+        .into_iter()
+        .find(|degree| s.starts_with(degree))
+        .map(|degree| s.split_at(degree.len()))
+}
+
+fn parse_suffix(suffix: &str) -> Option<(Quality, Vec<String>)> {
+    let mut rest = suffix;
+    let mut quality = Quality::Major;
+    for (prefix, q) in [
+        ("min", Quality::Minor),
+        ("mi", Quality::Minor),
+        ("dim", Quality::Diminished),
+        ("aug", Quality::Augmented),
+        ("°", Quality::Diminished),
+        ("o", Quality::Diminished),
+        ("ø", Quality::HalfDiminished),
+        ("+", Quality::Augmented),
+        ("-", Quality::Minor),
+    ] {
+        if let Some(tail) = rest.strip_prefix(prefix) {
+            quality = q;
+            rest = tail;
+            break;
+        }
+    }
+    if quality == Quality::Major && rest.starts_with('m') && !rest.starts_with("maj") {
+        quality = Quality::Minor;
+        rest = &rest[1..];
+    }
+
+    let mut extensions = Vec::new();
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix(['(', ')', ',', '/']) {
+            rest = tail;
+        } else if let Some(tail) = ["maj", "Maj", "M", "Δ"]
+            .into_iter()
+            .find_map(|prefix| rest.strip_prefix(prefix))
+        {
+            match parse_degree(tail) {
+                Some((degree, tail)) => {
+                    extensions.push(format!("maj{degree}"));
+                    rest = tail;
+                }
+                None => {
+                    // A bare "maj" or "M" just spells out the major triad, "Δ" means maj7.
+                    if rest.starts_with('Δ') {
+                        extensions.push(String::from("maj7"));
+                    }
+                    rest = tail;
+                }
+            }
+        } else if let Some(tail) = rest.strip_prefix("sus") {
+            match tail.strip_prefix(['2', '4']) {
+                Some(after) => {
+                    extensions.push(format!("sus{}", &tail[..1]));
+                    rest = after;
+                }
+                None => {
+                    extensions.push(String::from("sus4"));
+                    rest = tail;
+                }
+            }
+        } else if let Some(tail) = rest.strip_prefix("add") {
+            let (alteration, tail) = match tail.strip_prefix(['b', '#']) {
+                Some(after) => (&tail[..1], after),
+                None => ("", tail),
+            };
+            let (degree, tail) = parse_degree(tail)?;
+            extensions.push(format!("add{alteration}{degree}"));
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix("alt") {
+            extensions.push(String::from("alt"));
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix(['b', '♭', '-']) {
+            let (degree, tail) = parse_degree(tail)?;
+            extensions.push(format!("b{degree}"));
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix(['#', '♯', '+']) {
+            let (degree, tail) = parse_degree(tail)?;
+            extensions.push(format!("#{degree}"));
+            rest = tail;
+        } else {
+            let (degree, tail) = parse_degree(rest)?;
+            extensions.push(degree.to_string());
+            rest = tail;
+        }
+    }
+    if quality == Quality::HalfDiminished && extensions.is_empty() {
+        extensions.push(String::from("7"));
+    }
+    Some((quality, extensions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chords_print_as_they_were_written() {
+        for symbol in [
+            "C", "Am", "F#m7b5", "Bbmaj7", "Cmaj7/G", "Dsus4", "E7(#9)", "Gadd9", "Cø", "Bb°7",
+            "A-7", "C+", "DΔ", "Ebm/Gb",
+        ] {
+            assert_eq!(Chord::parse(symbol).unwrap().to_string(), symbol);
+        }
+    }
+
+    #[test]
+    fn chord_parts_are_recognized() {
+        let chord = Chord::parse("F#m7b5/C").unwrap();
+        assert_eq!(
+            chord.root,
+            Note {
+                letter: 'F',
+                accidental: 1
+            }
+        );
+        assert_eq!(chord.quality(), Quality::Minor);
+        assert_eq!(chord.extensions(), ["7", "b5"]);
+        assert_eq!(
+            chord.bass,
+            Some(Note {
+                letter: 'C',
+                accidental: 0
+            })
+        );
+        assert_eq!(Chord::parse("Cmin").unwrap().quality(), Quality::Minor);
+        assert_eq!(Chord::parse("CM7").unwrap().extensions(), ["maj7"]);
+        assert_eq!(Chord::parse("Cø").unwrap().extensions(), ["7"]);
+    }
+
+    #[test]
+    fn words_are_not_chords() {
+        for word in [
+            "", "H", "Hello", "Am I", "Cx", "Dmaj8", "a", "Bridge", "C/X",
+        ] {
+            assert_eq!(Chord::parse(word), None, "{word}");
+        }
+    }
+
+    fn transposed(symbol: &str, semitones: i32, prefer_flats: bool) -> String {
+        Chord::parse(symbol)
+            .unwrap()
+            .transpose(semitones, prefer_flats)
+            .to_string()
+    }
+
+    #[test]
+    fn transposing_spells_accidentals_as_requested() {
+        assert_eq!(transposed("C", 1, false), "C#");
+        assert_eq!(transposed("C", 1, true), "Db");
+        assert_eq!(transposed("A#m7", 0, true), "Bbm7");
+        assert_eq!(transposed("Bb", 2, false), "C");
+        assert_eq!(transposed("F#m7b5", 5, false), "Bm7b5");
+    }
+
+    #[test]
+    fn transposing_moves_the_bass_note_and_keeps_the_suffix() {
+        assert_eq!(transposed("Cmaj7/G", 3, true), "Ebmaj7/Bb");
+        assert_eq!(transposed("D/F#", -2, false), "C/E");
+        assert_eq!(transposed("E7(#9)", 12, false), "E7(#9)");
+    }
+
+    #[test]
+    fn transposing_wraps_around_the_octave() {
+        assert_eq!(transposed("B", 1, false), "C");
+        assert_eq!(transposed("C", -1, false), "B");
+        assert_eq!(transposed("G", -13, true), "Gb");
+        assert_eq!(transposed("Am", 25, false), "A#m");
+    }
+
+    fn nashville(symbol: &str, key: &str) -> String {
+        Chord::parse(symbol)
+            .unwrap()
+            .nashville(&Key::parse(key).unwrap())
+    }
+
+    #[test]
+    fn nashville_numbers_count_from_the_tonic() {
+        assert_eq!(nashville("G", "G"), "1");
+        assert_eq!(nashville("Em7", "G"), "6m⁷");
+        assert_eq!(nashville("C/D", "G"), "4/5");
+        assert_eq!(nashville("Dsus4", "D"), "1sus⁴");
+        assert_eq!(nashville("Bbmaj7", "F"), "4maj⁷");
+        assert_eq!(nashville("F#m7b5", "G"), "7m⁷b⁵");
+        assert_eq!(nashville("G13", "G"), "1¹³");
+    }
+
+    #[test]
+    fn nashville_numbers_flatten_notes_outside_the_major_scale() {
+        assert_eq!(nashville("F", "G"), "b7");
+        assert_eq!(nashville("Eb", "C"), "b3");
+        assert_eq!(nashville("C#", "C"), "b2");
+        assert_eq!(nashville("Db", "C"), "b2");
+        // Minor keys are counted from their own tonic as well.
+        assert_eq!(nashville("C", "Am"), "b3");
+        assert_eq!(nashville("E7", "Am"), "5⁷");
+    }
+}
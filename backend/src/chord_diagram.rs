@@ -0,0 +1,700 @@
+//! Fingerings of chords on fretted instruments and the piano, and diagrams that show them.
+
+use crate::chord::Chord;
+use juniper::{graphql_object, GraphQLEnum};
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// MIDI note numbers of the open strings of a guitar in standard tuning, from the lowest string.
+pub const STANDARD_GUITAR_TUNING: [u8; 6] = [40, 45, 50, 55, 59, 64];
+const DROP_D_GUITAR_TUNING: [u8; 6] = [38, 45, 50, 55, 59, 64];
+/// G C E A with the G string tuned above the C string.
+const UKULELE_TUNING: [u8; 4] = [67, 60, 64, 69];
+const BARITONE_UKULELE_TUNING: [u8; 4] = [50, 55, 59, 64];
+
+/// The chord shapes guitarists learn first, played in the first position with open strings.
+pub const OPEN_GUITAR_SHAPES: [(&str, &str); 36] = [
+    ("C", "x32010"),
+    ("C7", "x32310"),
+    ("Cmaj7", "x32000"),
+    ("Cadd9", "x32030"),
+    ("Csus4", "x33011"),
+    ("C/G", "332010"),
+    ("D", "xx0232"),
+    ("Dm", "xx0231"),
+    ("D7", "xx0212"),
+    ("Dm7", "xx0211"),
+    ("Dmaj7", "xx0222"),
+    ("Dsus2", "xx0230"),
+    ("Dsus4", "xx0233"),
+    ("D/F#", "200232"),
+    ("E", "022100"),
+    ("Em", "022000"),
+    ("E7", "020100"),
+    ("Em7", "020000"),
+    ("Emaj7", "021100"),
+    ("Esus4", "022200"),
+    ("Fmaj7", "xx3210"),
+    ("G", "320003"),
+    ("G7", "320001"),
+    ("Gmaj7", "320002"),
+    ("G6", "320000"),
+    ("G/B", "x20003"),
+    ("A", "x02220"),
+    ("Am", "x02210"),
+    ("A7", "x02020"),
+    ("Am7", "x02010"),
+    ("Amaj7", "x02120"),
+    ("Asus2", "x02200"),
+    ("Asus4", "x02230"),
+    ("Am/G", "302210"),
+    ("B7", "x21202"),
+    ("Bm7", "x20202"),
+];
+
+/// The number of frets a hand can comfortably cover without shifting.
+const HAND_SPAN: u8 = 4;
+const HIGHEST_POSITION: u8 = 12;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Voicing {
+    /// The fret played on every string from the lowest to the highest, `None` for a muted string
+    /// and `Some(0)` for an open string.
+    pub frets: Vec<Option<u8>>,
+}
+
+impl Voicing {
+    /// The lowest fret that is held down, or `1` if all strings are open or muted.
+    pub fn base_fret(&self) -> u8 {
+        self.frets
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|&fret| fret > 0)
+            .min()
+            .unwrap_or(1)
+    }
+
+    pub fn highest_fret(&self) -> u8 {
+        self.frets.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    fn fingers(&self) -> usize {
+        let fretted: Vec<u8> = self
+            .frets
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|&fret| fret > 0)
+            .collect();
+        let base = self.base_fret();
+        let first = self.frets.iter().position(|&f| f == Some(base));
+        let last = self.frets.iter().rposition(|&f| f == Some(base));
+        let barre = match (first, last) {
+            // A barre would also hold down the open strings it lies across.
+            (Some(first), Some(last)) if !self.frets[first..last].contains(&Some(0)) => {
+                fretted.iter().filter(|&&fret| fret == base).count()
+            }
+            _ => 0,
+        };
+        // All notes on the lowest fret can be held down by a single barre finger.
+        fretted.len() - barre.saturating_sub(1)
+    }
+
+    /// Open strings between fretted strings have to be avoided by the fingers, which gets harder
+    /// the further up the neck they reach.
+    fn enclosed_open_strings(&self) -> usize {
+        let first = self.frets.iter().position(|&f| f.is_some_and(|f| f > 0));
+        let last = self.frets.iter().rposition(|&f| f.is_some_and(|f| f > 0));
+        match (first, last) {
+            (Some(first), Some(last)) => self.frets[first..last]
+                .iter()
+                .filter(|&&f| f == Some(0))
+                .count(),
+            _ => 0,
+        }
+    }
+
+    /// Lower is better: prefers voicings low on the neck, with few fingers, a small stretch and
+    /// many ringing strings.
+    fn difficulty(&self) -> i32 {
+        let mut open = self.frets.iter().filter(|&&f| f == Some(0)).count() as i32;
+        if self.highest_fret() > 3 {
+            open = -open;
+        }
+        if self.highest_fret() > 2 {
+            open -= 2 * self.enclosed_open_strings() as i32;
+        }
+        let muted = self.frets.iter().filter(|f| f.is_none()).count() as i32;
+        let span = (self.highest_fret().max(self.base_fret()) - self.base_fret()) as i32;
+        let position = if self.highest_fret() <= HAND_SPAN {
+            0
+        } else {
+            self.base_fret() as i32
+        };
+        position * 3 + span * 2 + self.fingers() as i32 + muted * 2 - open
+    }
+}
+
+impl FromStr for Voicing {
+    type Err = String;
+
+    /// Parses the common tablature notation with one character per string, e.g. `x32010`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let frets = s
+            .chars()
+            .map(|c| match c {
+                'x' | 'X' => Ok(None),
+                _ => c
+                    .to_digit(10)
+                    .map(|fret| Some(fret as u8))
+                    .ok_or_else(|| format!("Invalid fret '{c}' in '{s}'.")),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Voicing { frets })
+    }
+}
+
+fn sounds_like(a: &Chord, b: &Chord) -> bool {
+    let (mut a, mut b) = (a.pitch_classes(), b.pitch_classes());
+    let bass_matches = a.first() == b.first();
+    a.sort();
+    b.sort();
+    bass_matches && a == b
+}
+
+/// The open-position shape of `chord` on a guitar in standard tuning, if it has one.
+pub fn open_guitar_shape(chord: &Chord) -> Option<Voicing> {
+    OPEN_GUITAR_SHAPES
+        .iter()
+        .find(|(symbol, _)| Chord::parse(symbol).is_some_and(|open| sounds_like(&open, chord)))
+        .and_then(|(_, shape)| shape.parse().ok())
+}
+
+/// Finds the easiest way to play `chord` on a fretted instrument with the given open strings.
+pub fn find_voicing(chord: &Chord, tuning: &[u8]) -> Option<Voicing> {
+    if tuning == STANDARD_GUITAR_TUNING {
+        if let Some(shape) = open_guitar_shape(chord) {
+            return Some(shape);
+        }
+    }
+    let tones = chord.pitch_classes();
+    let bass = *tones.first()?;
+    // The fifth adds little to the sound of a chord and is the first note to be left out.
+    let fifth = (chord.root.semitone() + 7) % 12;
+    let required: Vec<u8> = tones
+        .iter()
+        .copied()
+        .filter(|&tone| tone != fifth || tones.len() <= 3)
+        .take(tuning.len())
+        .collect();
+
+    // With re-entrant tunings like the ukulele's the lowest string is not the lowest note, and
+    // chords are commonly played without their root in the bass.
+    let reentrant = tuning.windows(2).any(|pair| pair[0] > pair[1]);
+    // Whether the frets of the lowest strings can still be completed to a playable voicing, so
+    // that the search does not have to try every fret on the remaining strings.
+    let viable = |frets: &[Option<u8>]| {
+        let muted_below = frets.iter().take_while(|f| f.is_none()).count();
+        let sounding: Vec<u8> = sounding_tones(frets, tuning).collect();
+        let missing = required
+            .iter()
+            .filter(|tone| !sounding.contains(tone))
+            .count();
+        muted_below + sounding.len() == frets.len()
+            && (reentrant || sounding.first().is_none_or(|&tone| tone == bass))
+            && missing <= tuning.len() - frets.len()
+            && Voicing {
+                frets: frets.to_vec(),
+            }
+            .fingers()
+                <= 4
+    };
+
+    let mut best: Option<Voicing> = None;
+    for position in 0..=HIGHEST_POSITION {
+        let lowest = position.max(1);
+        let options: Vec<Vec<Option<u8>>> = tuning
+            .iter()
+            .map(|&open| {
+                std::iter::once(None)
+                    .chain(
+                        std::iter::once(0)
+                            .chain(lowest..lowest + HAND_SPAN)
+                            .filter(|fret| tones.contains(&((open + fret) % 12)))
+                            .map(Some),
+                    )
+                    .collect()
+            })
+            .collect();
+        let mut frets = Vec::with_capacity(tuning.len());
+        search(&options, &mut frets, &viable, &mut |frets| {
+            let voicing = Voicing {
+                frets: frets.to_vec(),
+            };
+            if is_playable(&voicing, tuning, reentrant, bass, &required)
+                && best
+                    .as_ref()
+                    .is_none_or(|best| voicing.difficulty() < best.difficulty())
+            {
+                best = Some(voicing);
+            }
+        });
+    }
+    best
+}
+
+/// Visits every combination of the options for each string, leaving out those whose frets on the
+/// lowest strings are not `viable`.
+fn search(
+    options: &[Vec<Option<u8>>],
+    frets: &mut Vec<Option<u8>>,
+    viable: &impl Fn(&[Option<u8>]) -> bool,
+    visit: &mut impl FnMut(&[Option<u8>]),
+) {
+    let Some((string_options, rest)) = options.split_first() else {
+        visit(frets);
+        return;
+    };
+    for &fret in string_options {
+        frets.push(fret);
+        if viable(frets) {
+            search(rest, frets, viable, visit);
+        }
+        frets.pop();
+    }
+}
+
+/// The pitch classes of the strings that are played, from the lowest string.
+fn sounding_tones<'a>(frets: &'a [Option<u8>], tuning: &'a [u8]) -> impl Iterator<Item = u8> + 'a {
+    frets
+        .iter()
+        .zip(tuning)
+        .filter_map(|(fret, open)| fret.map(|fret| (open + fret) % 12))
+}
+
+fn is_playable(
+    voicing: &Voicing,
+    tuning: &[u8],
+    reentrant: bool,
+    bass: u8,
+    required: &[u8],
+) -> bool {
+    let sounding: Vec<u8> = sounding_tones(&voicing.frets, tuning).collect();
+    let muted_below = voicing.frets.iter().take_while(|f| f.is_none()).count();
+    sounding.len() >= tuning.len().min(4)
+        // Only the lowest strings may be left out, muting strings in between is hard to play.
+        && muted_below + sounding.len() == tuning.len()
+        && (reentrant || sounding.first() == Some(&bass))
+        && required.iter().all(|tone| sounding.contains(tone))
+        && voicing.highest_fret() < voicing.base_fret() + HAND_SPAN
+        && voicing.fingers() <= 4
+}
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instrument {
+    Guitar,
+    GuitarDropD,
+    Ukulele,
+    BaritoneUkulele,
+    Piano,
+}
+
+impl Instrument {
+    /// Parses the names used in URLs, e.g. `ukulele` or `guitar-drop-d`.
+    pub fn parse(s: &str) -> Option<Instrument> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "guitar" => Some(Instrument::Guitar),
+            "guitar-drop-d" => Some(Instrument::GuitarDropD),
+            "ukulele" => Some(Instrument::Ukulele),
+            "baritone-ukulele" => Some(Instrument::BaritoneUkulele),
+            "piano" => Some(Instrument::Piano),
+            _ => None,
+        }
+    }
+
+    /// The open strings from the lowest string, or `None` for instruments without strings.
+    pub fn tuning(self) -> Option<&'static [u8]> {
+        match self {
+            Instrument::Guitar => Some(&STANDARD_GUITAR_TUNING),
+            Instrument::GuitarDropD => Some(&DROP_D_GUITAR_TUNING),
+            Instrument::Ukulele => Some(&UKULELE_TUNING),
+            Instrument::BaritoneUkulele => Some(&BARITONE_UKULELE_TUNING),
+            Instrument::Piano => None,
+        }
+    }
+}
+
+/// The keys of a chord played in close position above middle C, with the bass note of a slash
+/// chord below the root.
+fn piano_keys(chord: &Chord) -> Vec<u8> {
+    let root = chord.root.semitone();
+    let above_root = |tone: u8| (tone + 12 - root) % 12;
+    let upper = Chord {
+        bass: None,
+        ..chord.clone()
+    };
+    let mut keys: Vec<u8> = upper
+        .pitch_classes()
+        .into_iter()
+        .map(|tone| 60 + root + above_root(tone))
+        .collect();
+    if let Some(bass) = &chord.bass {
+        keys.push(48 + root + above_root(bass.semitone()));
+    }
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fingering {
+    Fretted(Voicing),
+    /// MIDI note numbers of the keys to press.
+    Keyboard(Vec<u8>),
+}
+
+/// Where diagrams are drawn, with coordinates measured from the top left corner.
+pub trait Canvas {
+    fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32);
+    fn circle(&mut self, center: (f32, f32), radius: f32, filled: bool);
+    fn rect(&mut self, top_left: (f32, f32), size: (f32, f32), filled: bool);
+    /// Draws text horizontally centered on `position` with its baseline at it.
+    fn text(&mut self, position: (f32, f32), size: f32, bold: bool, text: &str);
+}
+
+pub const DIAGRAM_WIDTH: f32 = 60.0;
+pub const DIAGRAM_HEIGHT: f32 = 80.0;
+const NAME_SIZE: f32 = 10.0;
+const LABEL_SIZE: f32 = 7.0;
+/// Where the nut, or the top of the keys, is drawn.
+const GRID_TOP: f32 = 24.0;
+const STRING_SPACING: f32 = 8.0;
+const FRET_SPACING: f32 = 10.0;
+const DIAGRAM_FRETS: u8 = 4;
+const WHITE_KEY_WIDTH: f32 = 4.0;
+const WHITE_KEY_HEIGHT: f32 = 30.0;
+const BLACK_KEY_WIDTH: f32 = 2.6;
+const BLACK_KEY_HEIGHT: f32 = 18.0;
+/// The index of the white key for every pitch class, or of the white key left of a black key.
+const WHITE_KEY_INDEX: [usize; 12] = [0, 0, 1, 1, 2, 3, 3, 4, 4, 5, 5, 6];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChordDiagram {
+    pub name: String,
+    pub instrument: Instrument,
+    pub fingering: Fingering,
+}
+
+impl ChordDiagram {
+    /// The diagram of the easiest way to play `chord` on `instrument`, if there is one.
+    pub fn new(chord: &Chord, instrument: Instrument) -> Option<ChordDiagram> {
+        let fingering = match instrument.tuning() {
+            Some(tuning) => Fingering::Fretted(find_voicing(chord, tuning)?),
+            None => Fingering::Keyboard(piano_keys(chord)),
+        };
+        Some(ChordDiagram {
+            name: chord.to_string(),
+            instrument,
+            fingering,
+        })
+    }
+
+    /// The lowest C below the keys of a keyboard diagram and the number of octaves shown from it.
+    fn keyboard_range(keys: &[u8]) -> (u8, usize) {
+        let lowest = keys.iter().min().copied().unwrap_or(60);
+        let highest = keys.iter().max().copied().unwrap_or(60);
+        let first_c = lowest - lowest % 12;
+        (first_c, ((highest - first_c) / 12 + 1).max(2) as usize)
+    }
+
+    pub fn width(&self) -> f32 {
+        match &self.fingering {
+            Fingering::Fretted(_) => DIAGRAM_WIDTH,
+            Fingering::Keyboard(keys) => {
+                let (_, octaves) = Self::keyboard_range(keys);
+                DIAGRAM_WIDTH.max(7.0 * octaves as f32 * WHITE_KEY_WIDTH + 4.0)
+            }
+        }
+    }
+
+    pub fn draw(&self, canvas: &mut impl Canvas) {
+        canvas.text((self.width() / 2.0, NAME_SIZE), NAME_SIZE, true, &self.name);
+        match &self.fingering {
+            Fingering::Fretted(voicing) => self.draw_fretboard(canvas, voicing),
+            Fingering::Keyboard(keys) => self.draw_keyboard(canvas, keys),
+        }
+    }
+
+    fn draw_fretboard(&self, canvas: &mut impl Canvas, voicing: &Voicing) {
+        let strings = voicing.frets.len();
+        let grid_width = STRING_SPACING * (strings - 1) as f32;
+        let left = (self.width() - grid_width) / 2.0;
+        let first_fret = if voicing.highest_fret() <= DIAGRAM_FRETS {
+            1
+        } else {
+            voicing.base_fret()
+        };
+        for fret in 0..=DIAGRAM_FRETS {
+            let y = GRID_TOP + fret as f32 * FRET_SPACING;
+            let width = if fret == 0 && first_fret == 1 {
+                2.0
+            } else {
+                0.5
+            };
+            canvas.line((left, y), (left + grid_width, y), width);
+        }
+        for string in 0..strings {
+            let x = left + string as f32 * STRING_SPACING;
+            let bottom = GRID_TOP + DIAGRAM_FRETS as f32 * FRET_SPACING;
+            canvas.line((x, GRID_TOP), (x, bottom), 0.5);
+        }
+        if first_fret > 1 {
+            let label = format!("{first_fret}fr");
+            let x = left + grid_width + 3.0 + label.len() as f32 * LABEL_SIZE / 4.0;
+            canvas.text(
+                (x, GRID_TOP + FRET_SPACING - 2.0),
+                LABEL_SIZE,
+                false,
+                &label,
+            );
+        }
+        for (string, fret) in voicing.frets.iter().enumerate() {
+            let x = left + string as f32 * STRING_SPACING;
+            match fret {
+                None => canvas.text((x, GRID_TOP - 3.0), LABEL_SIZE, false, "x"),
+                Some(0) => canvas.circle((x, GRID_TOP - 5.0), 2.0, false),
+                Some(fret) => {
+                    let row = (*fret - first_fret) as f32;
+                    let y = GRID_TOP + row * FRET_SPACING + FRET_SPACING / 2.0;
+                    canvas.circle((x, y), 3.0, true);
+                }
+            }
+        }
+    }
+
+    fn draw_keyboard(&self, canvas: &mut impl Canvas, keys: &[u8]) {
+        let (first_c, octaves) = Self::keyboard_range(keys);
+        let white_keys = 7 * octaves;
+        let left = (self.width() - white_keys as f32 * WHITE_KEY_WIDTH) / 2.0;
+        let key_left = |key: u8| {
+            let offset = (key - first_c) as usize;
+            let white = 7 * (offset / 12) + WHITE_KEY_INDEX[offset % 12];
+            let x = left + white as f32 * WHITE_KEY_WIDTH;
+            if is_black_key(key) {
+                x + WHITE_KEY_WIDTH - BLACK_KEY_WIDTH / 2.0
+            } else {
+                x
+            }
+        };
+        let last = first_c + 12 * octaves as u8;
+        for key in (first_c..last).filter(|&key| !is_black_key(key)) {
+            let x = key_left(key);
+            canvas.rect((x, GRID_TOP), (WHITE_KEY_WIDTH, WHITE_KEY_HEIGHT), false);
+            if keys.contains(&key) {
+                let center = (x + WHITE_KEY_WIDTH / 2.0, GRID_TOP + WHITE_KEY_HEIGHT - 4.0);
+                canvas.circle(center, 1.5, true);
+            }
+        }
+        for key in (first_c..last).filter(|&key| is_black_key(key)) {
+            let x = key_left(key);
+            // Pressed black keys are drawn hollow so that the dot on them stands out.
+            let pressed = keys.contains(&key);
+            canvas.rect((x, GRID_TOP), (BLACK_KEY_WIDTH, BLACK_KEY_HEIGHT), !pressed);
+            if pressed {
+                let center = (x + BLACK_KEY_WIDTH / 2.0, GRID_TOP + BLACK_KEY_HEIGHT - 3.0);
+                canvas.circle(center, 1.0, true);
+            }
+        }
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = Svg::default();
+        self.draw(&mut svg);
+        let (width, height) = (self.width(), DIAGRAM_HEIGHT);
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {width} {height}\" \
+             width=\"{}\" height=\"{}\" font-family=\"Helvetica, Arial, sans-serif\">\n{}</svg>\n",
+            width * 2.0,
+            height * 2.0,
+            svg.0
+        )
+    }
+}
+
+#[graphql_object]
+#[graphql(name = "ChordDiagram")]
+impl ChordDiagram {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn instrument(&self) -> Instrument {
+        self.instrument
+    }
+
+    /// The fret to play on every string from the lowest, `null` for a muted string and `0` for an
+    /// open string. Not set for the piano.
+    fn frets(&self) -> Option<Vec<Option<i32>>> {
+        match &self.fingering {
+            Fingering::Fretted(voicing) => Some(
+                voicing
+                    .frets
+                    .iter()
+                    .map(|fret| fret.map(i32::from))
+                    .collect(),
+            ),
+            Fingering::Keyboard(_) => None,
+        }
+    }
+
+    /// The MIDI note numbers of the keys to press on a piano.
+    fn keys(&self) -> Option<Vec<i32>> {
+        match &self.fingering {
+            Fingering::Fretted(_) => None,
+            Fingering::Keyboard(keys) => Some(keys.iter().copied().map(i32::from).collect()),
+        }
+    }
+
+    /// The diagram as an SVG image.
+    fn svg(&self) -> String {
+        self.to_svg()
+    }
+}
+
+fn is_black_key(key: u8) -> bool {
+    matches!(key % 12, 1 | 3 | 6 | 8 | 10)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[derive(Default)]
+struct Svg(String);
+
+impl Canvas for Svg {
+    fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32) {
+        let _ = writeln!(
+            self.0,
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\" stroke-width=\"{width}\"/>",
+            from.0, from.1, to.0, to.1
+        );
+    }
+
+    fn circle(&mut self, (x, y): (f32, f32), radius: f32, filled: bool) {
+        let fill = if filled { "black" } else { "none" };
+        let _ = writeln!(
+            self.0,
+            "<circle cx=\"{x}\" cy=\"{y}\" r=\"{radius}\" fill=\"{fill}\" stroke=\"black\" stroke-width=\"0.8\"/>"
+        );
+    }
+
+    fn rect(&mut self, (x, y): (f32, f32), (width, height): (f32, f32), filled: bool) {
+        let fill = if filled { "black" } else { "white" };
+        let _ = writeln!(
+            self.0,
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{height}\" fill=\"{fill}\" stroke=\"black\" stroke-width=\"0.5\"/>"
+        );
+    }
+
+    fn text(&mut self, (x, y): (f32, f32), size: f32, bold: bool, text: &str) {
+        let weight = if bold { "bold" } else { "normal" };
+        let _ = writeln!(
+            self.0,
+            "<text x=\"{x}\" y=\"{y}\" font-size=\"{size}\" font-weight=\"{weight}\" text-anchor=\"middle\">{}</text>",
+            escape_xml(text)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frets(symbol: &str, instrument: Instrument) -> Option<String> {
+        let chord = Chord::parse(symbol).unwrap();
+        let voicing = find_voicing(&chord, instrument.tuning().unwrap())?;
+        Some(
+            voicing
+                .frets
+                .iter()
+                .map(|fret| fret.map_or('x', |fret| char::from_digit(fret.into(), 10).unwrap()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn open_chords_use_the_shapes_guitarists_know() {
+        assert_eq!(frets("C", Instrument::Guitar).as_deref(), Some("x32010"));
+        assert_eq!(frets("G7", Instrument::Guitar).as_deref(), Some("320001"));
+        assert_eq!(frets("D/F#", Instrument::Guitar).as_deref(), Some("200232"));
+    }
+
+    #[test]
+    fn other_chords_are_played_as_barre_chords() {
+        assert_eq!(frets("F", Instrument::Guitar).as_deref(), Some("133211"));
+        assert_eq!(frets("Bb", Instrument::Guitar).as_deref(), Some("x13331"));
+    }
+
+    #[test]
+    fn voicings_follow_the_tuning_of_the_instrument() {
+        assert_eq!(frets("C", Instrument::Ukulele).as_deref(), Some("0003"));
+        assert_eq!(frets("G7", Instrument::Ukulele).as_deref(), Some("0212"));
+        assert_eq!(
+            frets("D", Instrument::GuitarDropD).as_deref(),
+            Some("000232")
+        );
+    }
+
+    #[test]
+    fn chords_without_a_playable_voicing_have_no_diagram() {
+        let chord = Chord::parse("C6/9").unwrap();
+        assert_eq!(ChordDiagram::new(&chord, Instrument::BaritoneUkulele), None);
+    }
+
+    #[test]
+    fn piano_chords_are_played_above_middle_c() {
+        let keys = |symbol| piano_keys(&Chord::parse(symbol).unwrap());
+        assert_eq!(keys("C"), [60, 64, 67]);
+        assert_eq!(keys("Am7"), [69, 72, 76, 79]);
+        // The bass note of a slash chord is played an octave lower.
+        assert_eq!(keys("D/F#"), [54, 62, 66, 69]);
+    }
+
+    #[test]
+    fn svg_diagrams_show_every_string() {
+        let chord = Chord::parse("C").unwrap();
+        let svg = ChordDiagram::new(&chord, Instrument::Guitar)
+            .unwrap()
+            .to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 60 80\""));
+        assert!(svg.contains(">C</text>"));
+        assert_eq!(svg.matches(">x</text>").count(), 1);
+        assert_eq!(svg.matches("<circle").count(), 5);
+        assert_eq!(svg.matches("fill=\"none\"").count(), 2);
+        assert!(!svg.contains("fr</text>"));
+    }
+
+    #[test]
+    fn svg_diagrams_label_voicings_up_the_neck() {
+        let chord = Chord::parse("C13").unwrap();
+        let svg = ChordDiagram::new(&chord, Instrument::Guitar)
+            .unwrap()
+            .to_svg();
+        assert!(svg.contains(">3fr</text>"));
+    }
+
+    #[test]
+    fn svg_keyboards_mark_the_pressed_keys() {
+        let chord = Chord::parse("C#").unwrap();
+        let svg = ChordDiagram::new(&chord, Instrument::Piano)
+            .unwrap()
+            .to_svg();
+        // Two octaves of white keys, and the pressed black keys drawn hollow.
+        assert_eq!(svg.matches("fill=\"white\"").count(), 14 + 2);
+        assert_eq!(svg.matches("<circle").count(), 3);
+    }
+}
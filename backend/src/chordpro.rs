@@ -0,0 +1,216 @@
+//! Conversion between [`ParsedSong`] and the ChordPro format, where chords are written inline in
+//! square brackets (`[C]Hello [G]world`) and metadata is given by directives like `{title: ...}`.
+
+use crate::song_content::{section_label, ChordAnnotation, Line, ParsedSong, SongBuilder};
+
+pub struct ChordProSong {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub content: ParsedSong,
+}
+
+fn parse_directive(line: &str) -> Option<(String, Option<&str>)> {
+    let inner = line.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let (name, argument) = match inner.split_once(':') {
+        Some((name, argument)) => (name, Some(argument.trim())),
+        None => (inner, None),
+    };
+    Some((
+        name.trim().to_lowercase(),
+        argument.filter(|a| !a.is_empty()),
+    ))
+}
+
+/// Parses a line with inline chords like `[Am]Hello [G]world`.
+pub fn parse_inline_chords(text: &str) -> Line {
+    let mut line = Line::default();
+    let mut length = 0;
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        let Some(end) = rest[start..].find(']').map(|end| start + end) else {
+            break;
+        };
+        line.lyrics.push_str(&rest[..start]);
+        length += rest[..start].chars().count();
+        line.chords.push(ChordAnnotation {
+            offset: length as i32,
+            symbol: rest[start + 1..end].trim().to_string(),
+        });
+        rest = &rest[end + 1..];
+    }
+    line.lyrics.push_str(rest);
+    line.lyrics = line.lyrics.trim_end().to_string();
+    line
+}
+
+pub fn parse(text: &str) -> ChordProSong {
+    let mut title = None;
+    let mut artist = None;
+    let mut subtitle = None;
+    let mut builder = SongBuilder::new();
+    for line in text.lines() {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        let Some((directive, argument)) = parse_directive(line) else {
+            if line.contains('[') && section_label(line).is_none() {
+                builder.line(parse_inline_chords(line));
+            } else {
+                builder.text_line(line);
+            }
+            continue;
+        };
+        let section = |default: &str| Some(argument.unwrap_or(default).to_string());
+        match directive.as_str() {
+            "title" | "t" => title = argument.map(String::from),
+            "artist" => artist = argument.map(String::from),
+            "subtitle" | "st" => subtitle = argument.map(String::from),
+            "start_of_chorus" | "soc" => builder.start_section(section("Chorus")),
+            "start_of_verse" | "sov" => builder.start_section(section("Verse")),
+            "start_of_bridge" | "sob" => builder.start_section(section("Bridge")),
+            "start_of_tab" | "sot" => builder.start_section(section("Tab")),
+            "comment" | "c" | "comment_italic" | "ci" | "comment_box" | "cb" => {
+                if let Some(comment) = argument {
+                    builder.start_section(Some(comment.to_string()));
+                }
+            }
+            d if d.starts_with("end_of_") || matches!(d, "eoc" | "eov" | "eob" | "eot") => {
+                builder.start_section(None)
+            }
+            _ => {}
+        }
+    }
+    ChordProSong {
+        title,
+        // Many ChordPro files predate the artist directive and put the artist in the subtitle.
+        artist: artist.or(subtitle),
+        content: builder.finish(),
+    }
+}
+
+fn render_line(line: &Line) -> String {
+    let mut lyrics: Vec<char> = line.lyrics.chars().collect();
+    if let Some(last) = line.chords.last() {
+        let offset = last.offset.max(0) as usize;
+        if offset > lyrics.len() {
+            lyrics.resize(offset, ' ');
+        }
+    }
+    let mut rendered = String::new();
+    let mut position = 0;
+    for chord in &line.chords {
+        let offset = (chord.offset.max(0) as usize).clamp(position, lyrics.len());
+        rendered.extend(&lyrics[position..offset]);
+        rendered.push_str(&format!("[{}]", chord.symbol));
+        position = offset;
+    }
+    rendered.extend(&lyrics[position..]);
+    rendered.trim_end().to_string()
+}
+
+pub fn render(title: &str, artist: &str, content: &ParsedSong) -> String {
+    let mut text = String::new();
+    if !title.is_empty() {
+        text.push_str(&format!("{{title: {title}}}\n"));
+    }
+    if !artist.is_empty() {
+        text.push_str(&format!("{{artist: {artist}}}\n"));
+    }
+    for section in &content.sections {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        let environment = section.label.as_deref().and_then(|label| {
+            let name = label.split_whitespace().next()?.to_lowercase();
+            ["chorus", "verse", "bridge", "tab"]
+                .into_iter()
+                .find(|environment| *environment == name)
+                .map(|environment| (environment, label))
+        });
+        match (environment, &section.label) {
+            (Some((environment, label)), _) => {
+                text.push_str(&format!("{{start_of_{environment}: {label}}}\n"))
+            }
+            (None, Some(label)) => text.push_str(&format!("{{comment: {label}}}\n")),
+            (None, None) => {}
+        }
+        for line in &section.lines {
+            text.push_str(&render_line(line));
+            text.push('\n');
+        }
+        if let Some((environment, _)) = environment {
+            text.push_str(&format!("{{end_of_{environment}}}\n"));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(offset: i32, symbol: &str) -> ChordAnnotation {
+        ChordAnnotation {
+            offset,
+            symbol: symbol.to_string(),
+        }
+    }
+
+    #[test]
+    fn rendered_songs_parse_back_the_same() {
+        let text = "{title: Amazing Grace}\n{artist: John Newton}\n\n\
+            {start_of_verse: Verse 1}\n[G]Amazing [G7]grace, how [C]sweet the [G]sound\n\
+            {end_of_verse}\n\n{comment: Outro}\n[D] [G]\n";
+        let song = parse(text);
+        assert_eq!(song.title.as_deref(), Some("Amazing Grace"));
+        assert_eq!(song.artist.as_deref(), Some("John Newton"));
+        let rendered = render("Amazing Grace", "John Newton", &song.content);
+        assert_eq!(rendered, text);
+        assert_eq!(parse(&rendered).content, song.content);
+    }
+
+    #[test]
+    fn directives_set_the_details_and_the_sections() {
+        let song = parse(
+            "{t:Song}\n{st: Someone}\n# A remark\n{soc}\n[C]La la\n{eoc}\n{c: Slowly}\nLa\n{unknown: x}",
+        );
+        assert_eq!(song.title.as_deref(), Some("Song"));
+        // The subtitle stands in for the artist when there is no artist directive.
+        assert_eq!(song.artist.as_deref(), Some("Someone"));
+        let labels: Vec<Option<&str>> = song
+            .content
+            .sections
+            .iter()
+            .map(|section| section.label.as_deref())
+            .collect();
+        assert_eq!(labels, [Some("Chorus"), Some("Slowly")]);
+        assert_eq!(song.content.lyrics(), "La la\nLa");
+    }
+
+    #[test]
+    fn chords_may_be_placed_inside_words() {
+        let line = parse_inline_chords("Hal[Am]le[F]lujah, [C]hal");
+        assert_eq!(line.lyrics, "Hallelujah, hal");
+        assert_eq!(line.chords, [chord(3, "Am"), chord(5, "F"), chord(12, "C")]);
+        assert_eq!(render_line(&line), "Hal[Am]le[F]lujah, [C]hal");
+    }
+
+    #[test]
+    fn chords_after_the_lyrics_are_kept_in_place() {
+        let line = Line {
+            chords: vec![chord(0, "G"), chord(6, "D")],
+            lyrics: String::from("Hey"),
+        };
+        assert_eq!(render_line(&line), "[G]Hey   [D]");
+    }
+
+    #[test]
+    fn unclosed_brackets_are_lyrics() {
+        let line = parse_inline_chords("[G]Hello [world");
+        assert_eq!(line.chords, [chord(0, "G")]);
+        assert_eq!(line.lyrics, "Hello [world");
+        let line = parse_inline_chords("a] b [ C ]c");
+        assert_eq!(line.chords, [chord(5, "C")]);
+        assert_eq!(line.lyrics, "a] b c");
+    }
+}
@@ -1,8 +1,7 @@
 use crate::arguments::DatabaseArgs;
-use deadpool::managed::PoolError;
+use crate::error::{ChordmateError, ChordmateResult};
 use deadpool_postgres::{ManagerConfig, Object, Pool, RecyclingMethod, Runtime};
-use tokio::task::JoinHandle;
-use tokio_postgres::{Error, NoTls, Row};
+use tokio_postgres::NoTls;
 
 pub fn new_pool(args: DatabaseArgs) -> Pool {
     let config = args.config();
@@ -20,22 +19,35 @@ pub fn new_pool(args: DatabaseArgs) -> Pool {
         .unwrap()
 }
 
+/// A handle to the database, cheap to clone. A connection without a pool, e.g. for running the
+/// schema in tests, fails every query with `DB_UNAVAILABLE`.
+#[derive(Clone)]
 pub struct DatabaseConnection {
-    pub connection_pool: Pool,
+    connection_pool: Option<Pool>,
 }
 
 impl DatabaseConnection {
-    pub fn query(self: &Self, q: &str) -> JoinHandle<Result<Row, Error>> {
-        // clone only creates a new handle to the same pool. It's using Arc internally.
-        let pool = self.connection_pool.clone();
-        let q = String::from(q);
-        tokio::spawn(async move { pool.get().await.unwrap().query_one(&q, &[]).await })
+    pub fn new(connection_pool: Pool) -> Self {
+        DatabaseConnection {
+            connection_pool: Some(connection_pool),
+        }
     }
 
-    pub async fn get(&self) -> Result<Object, PoolError<Error>> {
-        let pool = self.connection_pool.clone();
-        pool.get()
+    pub fn disconnected() -> Self {
+        DatabaseConnection {
+            connection_pool: None,
+        }
+    }
+
+    pub async fn get(&self) -> ChordmateResult<Object> {
+        let Some(pool) = &self.connection_pool else {
+            return Err(ChordmateError::DbUnavailable(String::from(
+                "There is no database to connect to.",
+            )));
+        };
+        Ok(pool
+            .get()
             .await
-            .inspect_err(|error| eprintln!("Error: {error}"))
+            .inspect_err(|error| eprintln!("Error: {error}"))?)
     }
 }
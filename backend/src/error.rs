@@ -0,0 +1,258 @@
+//! The errors resolvers fail with, and the GraphQL error codes clients see for them.
+
+use crate::spotify::TokenError;
+use deadpool::managed::PoolError;
+use juniper::{graphql_value, FieldError, IntoFieldError, ScalarValue, Value};
+use log::error;
+use std::fmt;
+use tokio_postgres::error::SqlState;
+
+#[derive(Debug, PartialEq)]
+pub enum ChordmateError {
+    /// `NOT_FOUND`: something the request refers to does not exist.
+    NotFound(String),
+    /// `UNAUTHENTICATED`: the request needs a logged in user.
+    Unauthenticated(String),
+    /// `FORBIDDEN`: the user is logged in, but may not do this.
+    Forbidden(String),
+    /// `SPOTIFY_NOT_CONNECTED`: nobody has logged in to Spotify yet.
+    SpotifyNotConnected(String),
+    /// `SPOTIFY_TOKEN_ERROR`: Spotify did not hand out an access token, logging in to Spotify
+    /// again may help.
+    SpotifyTokenError(String),
+    /// `SPOTIFY_REQUEST_FAILED`: Spotify could not be reached or answered with an error.
+    SpotifyRequestFailed(String),
+    /// `USERNAME_TAKEN`: someone already signed up with the username.
+    UsernameTaken(String),
+    /// `VALIDATION_ERROR`: the input is invalid. Holds the field and the problem for every
+    /// violation, which are listed as `fields` in the extensions.
+    Validation(Vec<(String, String)>),
+    /// `DB_UNAVAILABLE`: the database could not be reached.
+    DbUnavailable(String),
+    /// `INTERNAL_SERVER_ERROR`: a query or something else failed that should not have. The details
+    /// are logged rather than sent to the client.
+    Internal(String),
+}
+
+pub type ChordmateResult<T> = Result<T, ChordmateError>;
+
+impl fmt::Display for ChordmateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChordmateError::NotFound(message)
+            | ChordmateError::Unauthenticated(message)
+            | ChordmateError::Forbidden(message)
+            | ChordmateError::SpotifyNotConnected(message)
+            | ChordmateError::SpotifyTokenError(message)
+            | ChordmateError::SpotifyRequestFailed(message)
+            | ChordmateError::UsernameTaken(message)
+            | ChordmateError::DbUnavailable(message)
+            | ChordmateError::Internal(message) => f.write_str(message),
+            ChordmateError::Validation(violations) => f.write_str(&joined_messages(violations)),
+        }
+    }
+}
+
+impl std::error::Error for ChordmateError {}
+
+fn joined_messages(violations: &[(String, String)]) -> String {
+    violations
+        .iter()
+        .map(|(_, message)| message.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl From<tokio_postgres::Error> for ChordmateError {
+    fn from(error: tokio_postgres::Error) -> Self {
+        if error.is_closed() {
+            return ChordmateError::DbUnavailable(format!(
+                "The database connection broke: {error}"
+            ));
+        }
+        match error.code() {
+            // Ids of rows that were deleted in the meantime, or never existed.
+            Some(&SqlState::FOREIGN_KEY_VIOLATION) => {
+                ChordmateError::NotFound(String::from("A referenced item does not exist."))
+            }
+            Some(code)
+                if code == &SqlState::ADMIN_SHUTDOWN
+                    || code == &SqlState::CANNOT_CONNECT_NOW
+                    || code == &SqlState::TOO_MANY_CONNECTIONS =>
+            {
+                ChordmateError::DbUnavailable(format!("The database is not available: {error}"))
+            }
+            _ => ChordmateError::Internal(format!("A database query failed: {error}")),
+        }
+    }
+}
+
+impl From<PoolError<tokio_postgres::Error>> for ChordmateError {
+    fn from(error: PoolError<tokio_postgres::Error>) -> Self {
+        ChordmateError::DbUnavailable(format!("No database connection available: {error}"))
+    }
+}
+
+impl From<TokenError> for ChordmateError {
+    fn from(error: TokenError) -> Self {
+        match error {
+            TokenError::Missing => {
+                ChordmateError::SpotifyNotConnected(String::from("Spotify is not connected."))
+            }
+            TokenError::FailedToGet(message) => ChordmateError::SpotifyTokenError(format!(
+                "Failed to get a Spotify token: {message}"
+            )),
+        }
+    }
+}
+
+impl From<argon2::password_hash::Error> for ChordmateError {
+    fn from(error: argon2::password_hash::Error) -> Self {
+        ChordmateError::Internal(format!("Password hashing failed: {error}"))
+    }
+}
+
+impl From<tokio::task::JoinError> for ChordmateError {
+    fn from(error: tokio::task::JoinError) -> Self {
+        ChordmateError::Internal(format!("A background task failed: {error}"))
+    }
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for ChordmateError {
+    fn into_field_error(self) -> FieldError<S> {
+        let (message, code) = match self {
+            ChordmateError::Validation(violations) => {
+                let message = joined_messages(&violations);
+                let fields = Value::list(
+                    violations
+                        .into_iter()
+                        .map(
+                            |(field, message)| graphql_value!({"field": field, "message": message}),
+                        )
+                        .collect(),
+                );
+                return FieldError::new(
+                    message,
+                    graphql_value!({"code": "VALIDATION_ERROR", "fields": fields}),
+                );
+            }
+            ChordmateError::NotFound(message) => (message, "NOT_FOUND"),
+            ChordmateError::Unauthenticated(message) => (message, "UNAUTHENTICATED"),
+            ChordmateError::Forbidden(message) => (message, "FORBIDDEN"),
+            ChordmateError::SpotifyNotConnected(message) => (message, "SPOTIFY_NOT_CONNECTED"),
+            ChordmateError::SpotifyTokenError(message) => (message, "SPOTIFY_TOKEN_ERROR"),
+            ChordmateError::SpotifyRequestFailed(message) => (message, "SPOTIFY_REQUEST_FAILED"),
+            ChordmateError::UsernameTaken(message) => (message, "USERNAME_TAKEN"),
+            ChordmateError::DbUnavailable(message) => {
+                error!("{message}");
+                let message = "The database is not available, please try again later.";
+                (message.to_string(), "DB_UNAVAILABLE")
+            }
+            ChordmateError::Internal(message) => {
+                error!("{message}");
+                let message = "Something went wrong on the server.";
+                (message.to_string(), "INTERNAL_SERVER_ERROR")
+            }
+        };
+        FieldError::new(message, graphql_value!({"code": code}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use juniper::DefaultScalarValue;
+
+    fn field_error(error: ChordmateError) -> FieldError {
+        IntoFieldError::<DefaultScalarValue>::into_field_error(error)
+    }
+
+    #[test]
+    fn every_error_carries_its_code() {
+        for (error, code) in [
+            (ChordmateError::NotFound("No song 1.".into()), "NOT_FOUND"),
+            (
+                ChordmateError::Unauthenticated("Log in first.".into()),
+                "UNAUTHENTICATED",
+            ),
+            (ChordmateError::Forbidden("Not yours.".into()), "FORBIDDEN"),
+            (
+                ChordmateError::SpotifyNotConnected("Connect Spotify.".into()),
+                "SPOTIFY_NOT_CONNECTED",
+            ),
+            (
+                ChordmateError::SpotifyTokenError("No token.".into()),
+                "SPOTIFY_TOKEN_ERROR",
+            ),
+            (
+                ChordmateError::SpotifyRequestFailed("Spotify returned 500.".into()),
+                "SPOTIFY_REQUEST_FAILED",
+            ),
+            (
+                ChordmateError::UsernameTaken("The username 'ann' is taken.".into()),
+                "USERNAME_TAKEN",
+            ),
+        ] {
+            let message = error.to_string();
+            let error = field_error(error);
+            assert_eq!(error.message(), message);
+            assert_eq!(error.extensions(), &graphql_value!({"code": code}));
+        }
+    }
+
+    #[test]
+    fn server_errors_do_not_leak_their_details() {
+        let error = field_error(ChordmateError::DbUnavailable("Connection refused".into()));
+        assert_eq!(
+            error.message(),
+            "The database is not available, please try again later."
+        );
+        assert_eq!(
+            error.extensions(),
+            &graphql_value!({"code": "DB_UNAVAILABLE"})
+        );
+
+        let error = field_error(ChordmateError::Internal("syntax error at or near".into()));
+        assert_eq!(error.message(), "Something went wrong on the server.");
+        assert_eq!(
+            error.extensions(),
+            &graphql_value!({"code": "INTERNAL_SERVER_ERROR"})
+        );
+    }
+
+    #[test]
+    fn validation_errors_list_every_field() {
+        let error = field_error(ChordmateError::Validation(vec![
+            ("title".into(), "`title` must not be empty.".into()),
+            ("bpm".into(), "`bpm` must be between 1 and 400.".into()),
+        ]));
+        assert_eq!(
+            error.message(),
+            "`title` must not be empty. `bpm` must be between 1 and 400."
+        );
+        assert_eq!(
+            error.extensions(),
+            &graphql_value!({
+                "code": "VALIDATION_ERROR",
+                "fields": [
+                    {"field": "title", "message": "`title` must not be empty."},
+                    {"field": "bpm", "message": "`bpm` must be between 1 and 400."},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn spotify_token_errors_keep_their_kind() {
+        assert_eq!(
+            ChordmateError::from(TokenError::Missing),
+            ChordmateError::SpotifyNotConnected("Spotify is not connected.".into())
+        );
+        assert_eq!(
+            ChordmateError::from(TokenError::FailedToGet("invalid_grant".into())),
+            ChordmateError::SpotifyTokenError(
+                "Failed to get a Spotify token: invalid_grant".into()
+            )
+        );
+    }
+}
@@ -0,0 +1,597 @@
+//! A minimal HTML tokenizer for the markup produced by the song editor.
+//!
+//! The editor only emits a small, well-formed subset of HTML (paragraphs, headings, lists and
+//! inline formatting), so there is no need for a full HTML5 parser here. Content from clients is
+//! [`sanitize`]d down to that subset before it is stored.
+
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    StartTag {
+        name: String,
+        attributes: Vec<(String, String)>,
+        self_closing: bool,
+    },
+    EndTag {
+        name: String,
+    },
+    Text(String),
+    Comment(String),
+}
+
+pub fn tokenize(html: &str) -> Vec<Token> {
+    tokenize_with_ranges(html)
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect()
+}
+
+/// Like [`tokenize`], together with the range of the document each token was read from.
+fn tokenize_with_ranges(html: &str) -> Vec<(Token, Range<usize>)> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let start = html.len() - rest.len();
+        let token = if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").unwrap_or(comment.len());
+            let token = Token::Comment(comment[..end].to_string());
+            rest = comment.get(end + 3..).unwrap_or("");
+            token
+        } else if let Some((token, tail)) = rest.strip_prefix('<').and_then(parse_tag) {
+            rest = tail;
+            token
+        } else {
+            // A '<' that does not start a tag is treated as text.
+            let skip = if rest.starts_with('<') { 1 } else { 0 };
+            let end = rest[skip..].find('<').map_or(rest.len(), |i| i + skip);
+            let token = Token::Text(decode_entities(&rest[..end]));
+            rest = &rest[end..];
+            token
+        };
+        tokens.push((token, start..html.len() - rest.len()));
+    }
+    tokens
+}
+
+fn parse_tag(s: &str) -> Option<(Token, &str)> {
+    let (is_end, s) = match s.strip_prefix('/') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let name_len = s
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .unwrap_or(s.len());
+    if name_len == 0 || !s.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name = s[..name_len].to_ascii_lowercase();
+    let mut rest = &s[name_len..];
+    let mut attributes = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if let Some(tail) = rest.strip_prefix("/>") {
+            let token = if is_end {
+                Token::EndTag { name }
+            } else {
+                Token::StartTag {
+                    name,
+                    attributes,
+                    self_closing: true,
+                }
+            };
+            return Some((token, tail));
+        }
+        if let Some(tail) = rest.strip_prefix('>') {
+            let token = if is_end {
+                Token::EndTag { name }
+            } else {
+                Token::StartTag {
+                    name,
+                    attributes,
+                    self_closing: false,
+                }
+            };
+            return Some((token, tail));
+        }
+        let (attribute, tail) = parse_attribute(rest)?;
+        attributes.push(attribute);
+        rest = tail;
+    }
+}
+
+fn parse_attribute(s: &str) -> Option<((String, String), &str)> {
+    let name_len = s
+        .find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/' | '"' | '\''))
+        .unwrap_or(s.len());
+    if name_len == 0 {
+        return None;
+    }
+    let name = s[..name_len].to_ascii_lowercase();
+    let rest = s[name_len..].trim_start();
+    let Some(rest) = rest.strip_prefix('=') else {
+        return Some(((name, String::new()), rest));
+    };
+    let rest = rest.trim_start();
+    let (value, tail) = match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let end = rest[1..].find(quote)? + 1;
+            (&rest[1..end], &rest[end + 1..])
+        }
+        _ => {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '>')
+                .unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        }
+    };
+    Some(((name, decode_entities(value)), tail))
+}
+
+pub fn decode_entities(s: &str) -> String {
+    decode_with_ranges(s).into_iter().map(|(c, _)| c).collect()
+}
+
+/// Decodes the entities in `s`, keeping the range of `s` every character was decoded from.
+pub fn decode_with_ranges(s: &str) -> Vec<(char, Range<usize>)> {
+    let mut decoded = Vec::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        let start = s.len() - rest.len();
+        let entity = rest.strip_prefix('&').and_then(|entity| {
+            entity
+                .find(';')
+                .filter(|&end| end <= 10)
+                .map(|end| &entity[..end])
+        });
+        let (c, length) = match entity.and_then(|entity| Some((decode_entity(entity)?, entity))) {
+            Some((decoded, entity)) => (decoded, entity.len() + 2),
+            None => (c, c.len_utf8()),
+        };
+        decoded.push((c, start..start + length));
+        rest = &rest[length..];
+    }
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        // The editor uses non-breaking spaces to preserve runs of spaces, which are significant
+        // for aligning chords above lyrics.
+        "nbsp" => Some(' '),
+        _ => {
+            let code = if let Some(hex) = entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                u32::from_str_radix(hex, 16).ok()?
+            } else {
+                entity.strip_prefix('#')?.parse().ok()?
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Like [`escape`], but keeps runs of spaces from collapsing when the HTML is rendered or loaded
+/// into the editor.
+pub fn escape_preserving_spaces(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut escaped = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let is_single_inner_space = c == ' '
+            && i > 0
+            && chars[i - 1] != ' '
+            && chars.get(i + 1).is_some_and(|&next| next != ' ');
+        if c == ' ' && !is_single_inner_space {
+            escaped.push_str("&nbsp;");
+        } else {
+            escaped.push_str(&escape(&c.to_string()));
+        }
+    }
+    escaped
+}
+
+/// Elements whose content is dropped along with them, rather than kept as text.
+fn is_dropped_with_content(name: &str) -> bool {
+    matches!(
+        name,
+        "script"
+            | "style"
+            | "iframe"
+            | "object"
+            | "noscript"
+            | "template"
+            | "textarea"
+            | "select"
+            | "svg"
+            | "math"
+            | "title"
+            | "head"
+    )
+}
+
+/// The elements the song editor produces.
+fn is_allowed(name: &str) -> bool {
+    is_block(name)
+        || matches!(
+            name,
+            "br" | "code" | "strong" | "b" | "em" | "i" | "s" | "u" | "a"
+        )
+}
+
+/// Elements that never have content or an end tag.
+fn is_void(name: &str) -> bool {
+    matches!(
+        name,
+        "area"
+            | "base"
+            | "br"
+            | "col"
+            | "embed"
+            | "hr"
+            | "img"
+            | "input"
+            | "link"
+            | "meta"
+            | "source"
+            | "track"
+            | "wbr"
+    )
+}
+
+/// Checks that every element is closed, in the reverse order of being opened.
+pub fn check_well_formed(html: &str) -> Result<(), String> {
+    let mut open: Vec<String> = Vec::new();
+    for token in tokenize(html) {
+        match token {
+            Token::StartTag {
+                name,
+                self_closing: false,
+                ..
+            } if !is_void(&name) => open.push(name),
+            Token::EndTag { name } if !is_void(&name) => match open.pop() {
+                Some(opened) if opened == name => {}
+                Some(opened) => return Err(format!("<{opened}> is closed by </{name}>.")),
+                None => {
+                    return Err(format!(
+                        "</{name}> closes an element that was never opened."
+                    ))
+                }
+            },
+            _ => {}
+        }
+    }
+    match open.pop() {
+        Some(name) => Err(format!("<{name}> is never closed.")),
+        None => Ok(()),
+    }
+}
+
+/// Links may only point to web pages and mail addresses, or be relative.
+fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters in the scheme, e.g. `java\tscript:`.
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    match url.find(':') {
+        Some(colon) if !url[..colon].contains(['/', '?', '#']) => {
+            matches!(&url[..colon], "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+fn is_allowed_attribute(element: &str, name: &str, value: &str) -> bool {
+    match (element, name) {
+        ("a", "href") => is_safe_url(value),
+        ("a", "target") => value == "_blank",
+        ("a", "rel") => true,
+        ("ol", "start") => value.parse::<u32>().is_ok(),
+        ("code", "class") => value
+            .strip_prefix("language-")
+            .is_some_and(|language| language.chars().all(|c| c.is_ascii_alphanumeric())),
+        _ => false,
+    }
+}
+
+/// Keeps only the elements and attributes the song editor produces, so that content sent by a
+/// client cannot run scripts for the other users it is shown to.
+///
+/// Scripts, styles and embedded documents are removed with their content, other unknown elements
+/// are replaced by their content. Runs of spaces are written as non-breaking spaces.
+pub fn sanitize(html: &str) -> String {
+    let mut sanitized = String::with_capacity(html.len());
+    // The element being dropped with its content, and how deeply it is nested in itself.
+    let mut dropping: Option<(String, usize)> = None;
+    let mut line_start = true;
+    for token in tokenize(html) {
+        if let Some((dropped, depth)) = &mut dropping {
+            match &token {
+                Token::StartTag {
+                    name,
+                    self_closing: false,
+                    ..
+                } if name == dropped => *depth += 1,
+                Token::EndTag { name } if name == dropped => {
+                    *depth -= 1;
+                    if *depth == 0 {
+                        dropping = None;
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match token {
+            Token::StartTag {
+                name,
+                self_closing: false,
+                ..
+            } if is_dropped_with_content(&name) => dropping = Some((name, 1)),
+            Token::StartTag {
+                name, attributes, ..
+            } if is_allowed(&name) => {
+                sanitized.push('<');
+                sanitized.push_str(&name);
+                for (attribute, value) in attributes {
+                    if is_allowed_attribute(&name, &attribute, &value) {
+                        sanitized.push_str(&format!(" {attribute}=\"{}\"", escape(&value)));
+                    }
+                }
+                sanitized.push('>');
+                line_start = is_block(&name) || name == "br";
+            }
+            Token::EndTag { name } if is_allowed(&name) && !is_void(&name) => {
+                sanitized.push_str(&format!("</{name}>"));
+                line_start = is_block(&name);
+            }
+            Token::Text(text) => {
+                sanitized.push_str(&escape_text(&text, line_start));
+                line_start = false;
+            }
+            _ => {}
+        }
+    }
+    sanitized
+}
+
+/// Escapes a piece of text that sits between tags. Single spaces next to inline tags are kept as
+/// ordinary spaces, only at the start of a line would they collapse.
+fn escape_text(text: &str, line_start: bool) -> String {
+    // Neighbours that are not spaces keep single spaces at the ends from being treated as runs.
+    let padded = format!("{}{text}x", if line_start { "" } else { "x" });
+    let escaped = escape_preserving_spaces(&padded);
+    let escaped = escaped.strip_suffix('x').unwrap_or(&escaped);
+    let escaped = if line_start { escaped } else { &escaped[1..] };
+    escaped.to_string()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockKind {
+    Paragraph,
+    Heading(u8),
+}
+
+/// A single line of text from the document, stripped of all markup.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextLine {
+    pub kind: BlockKind,
+    pub text: String,
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "li"
+            | "ul"
+            | "ol"
+            | "pre"
+            | "blockquote"
+            | "hr"
+    )
+}
+
+fn heading_level(name: &str) -> Option<u8> {
+    match name.as_bytes() {
+        [b'h', level @ b'1'..=b'6'] => Some(level - b'0'),
+        _ => None,
+    }
+}
+
+/// Splits an HTML document into its lines of plain text.
+///
+/// Every block element and every `<br>` starts a new line, an empty paragraph yields an empty
+/// line. Inline markup is dropped.
+pub fn text_lines(html: &str) -> Vec<TextLine> {
+    source_lines(html)
+        .into_iter()
+        .map(|line| TextLine {
+            kind: line.kind,
+            text: line
+                .ranges
+                .iter()
+                .map(|range| decode_entities(&html[range.clone()]).replace('\n', " "))
+                .collect(),
+        })
+        .collect()
+}
+
+/// A line of text like [`TextLine`], given as the ranges of the document its text is written in,
+/// so that it can be changed without touching the markup around it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLine {
+    pub kind: BlockKind,
+    pub ranges: Vec<Range<usize>>,
+}
+
+/// The lines of [`text_lines`], as ranges of the document.
+pub fn source_lines(html: &str) -> Vec<SourceLine> {
+    let mut lines = Vec::new();
+    let mut current: Option<SourceLine> = None;
+    let mut kind = BlockKind::Paragraph;
+    let mut preformatted = false;
+
+    fn flush(lines: &mut Vec<SourceLine>, current: &mut Option<SourceLine>) {
+        if let Some(line) = current.take() {
+            lines.push(line);
+        }
+    }
+
+    let empty = |kind| SourceLine {
+        kind,
+        ranges: Vec::new(),
+    };
+    for (token, range) in tokenize_with_ranges(html) {
+        match token {
+            Token::StartTag { name, .. } if name == "br" => {
+                lines.push(current.take().unwrap_or(empty(kind)));
+                current = Some(empty(kind));
+            }
+            Token::StartTag { name, .. } if is_block(&name) => {
+                flush(&mut lines, &mut current);
+                kind = heading_level(&name).map_or(BlockKind::Paragraph, BlockKind::Heading);
+                preformatted |= name == "pre";
+                if matches!(name.as_str(), "p" | "div" | "li") || heading_level(&name).is_some() {
+                    current = Some(empty(kind));
+                }
+            }
+            Token::EndTag { name } if is_block(&name) => {
+                flush(&mut lines, &mut current);
+                kind = BlockKind::Paragraph;
+                preformatted &= name != "pre";
+            }
+            Token::Text(text) => {
+                if preformatted {
+                    let mut start = range.start;
+                    for (i, _) in html[range.clone()].match_indices('\n') {
+                        let end = range.start + i;
+                        current.get_or_insert(empty(kind)).ranges.push(start..end);
+                        flush(&mut lines, &mut current);
+                        current = Some(empty(kind));
+                        start = end + 1;
+                    }
+                    current
+                        .get_or_insert(empty(kind))
+                        .ranges
+                        .push(start..range.end);
+                } else if current.is_some() || !text.trim().is_empty() {
+                    current.get_or_insert(empty(kind)).ranges.push(range);
+                }
+            }
+            _ => {}
+        }
+    }
+    flush(&mut lines, &mut current);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_and_styles_are_removed_with_their_content() {
+        assert_eq!(
+            sanitize("<p>Verse</p><script>alert(1)</script><style>p { color: red }</style>"),
+            "<p>Verse</p>"
+        );
+        assert_eq!(
+            sanitize("<p>A<SCRIPT type=\"module\">alert(1)</SCRIPT>B</p>"),
+            "<p>AB</p>"
+        );
+    }
+
+    #[test]
+    fn event_handler_attributes_are_removed() {
+        assert_eq!(
+            sanitize(
+                "<p onclick=\"alert(1)\">Verse</p><a href=\"/x\" onmouseover='alert(1)'>x</a>"
+            ),
+            "<p>Verse</p><a href=\"/x\">x</a>"
+        );
+    }
+
+    #[test]
+    fn links_to_scripts_and_data_are_removed() {
+        for href in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "  javascript:alert(1)",
+            "java\tscript:alert(1)",
+            "java&#x09;script:alert(1)",
+            "data:text/html;base64,PHNjcmlwdD4=",
+            " DATA:text/html,x",
+        ] {
+            assert_eq!(
+                sanitize(&format!("<a href=\"{href}\">x</a>")),
+                "<a>x</a>",
+                "{href}"
+            );
+        }
+        assert_eq!(
+            sanitize("<a href=\"https://example.com/?a=1&amp;b=2\">x</a>"),
+            "<a href=\"https://example.com/?a=1&amp;b=2\">x</a>"
+        );
+        assert_eq!(
+            sanitize("<a href=\"chords/javascript:x\">x</a>"),
+            "<a href=\"chords/javascript:x\">x</a>"
+        );
+    }
+
+    #[test]
+    fn comments_are_removed() {
+        assert_eq!(
+            sanitize("<p>A<!-- <script>alert(1)</script> -->B</p><!-- unclosed"),
+            "<p>AB</p>"
+        );
+    }
+
+    #[test]
+    fn self_closing_and_unclosed_dropped_elements() {
+        // A self-closing script has no content, so what follows it is kept.
+        assert_eq!(sanitize("<script/><p>Verse</p>"), "<p>Verse</p>");
+        // An unclosed script takes the rest of the document with it.
+        assert_eq!(
+            sanitize("<p>Verse</p><script>alert(1)<p>Chorus</p>"),
+            "<p>Verse</p>"
+        );
+        assert_eq!(
+            sanitize("<style><style></style>p {}</style><p>Verse</p>"),
+            "<p>Verse</p>"
+        );
+    }
+
+    #[test]
+    fn unknown_elements_are_replaced_by_their_content() {
+        assert_eq!(
+            sanitize("<p><span style=\"color: red\">Verse</span></p><img src=x onerror=alert(1)>"),
+            "<p>Verse</p>"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Guessing the key of a song from its chords.
+//!
+//! Every key is scored by how well the chords of the song fit its scale, counting chords that are
+//! played often more, and giving extra weight to songs that start or end on the key's tonic chord.
+
+use crate::chord::{Chord, Key, Note, Quality};
+use crate::song_content::ParsedSong;
+use juniper::GraphQLObject;
+
+#[derive(GraphQLObject, Clone, Debug, PartialEq)]
+pub struct DetectedKey {
+    /// E.g. `G` or `F#m`.
+    pub key: String,
+    /// Between 0 and 1, how sure the guess is.
+    pub confidence: f64,
+}
+
+/// How much the first and the last chord count towards the key they are the tonic chord of.
+const TONIC_AT_START_OR_END: f64 = 3.0;
+/// How much playing the tonic chord counts in addition to it fitting the scale.
+const TONIC_EMPHASIS: f64 = 0.5;
+/// How sharply the confidence drops as other keys fit almost as well.
+const SHARPNESS: f64 = 6.0;
+
+/// How well a chord fits a key: 1 for chords of its scale, less for chords commonly borrowed from
+/// other modes, 0 for anything else.
+fn fit(key: &Key, chord: &Chord) -> f64 {
+    let degree = (chord.root.semitone() + 12 - key.tonic.semitone()) % 12;
+    let quality = match chord.quality() {
+        Quality::HalfDiminished => Quality::Diminished,
+        quality => quality,
+    };
+    use Quality::*;
+    match (key.minor, degree, quality) {
+        (false, 0 | 5 | 7, Major) | (false, 2 | 4 | 9, Minor) | (false, 11, Diminished) => 1.0,
+        // The flat seventh of rock songs and the minor fourth.
+        (false, 10, Major) | (false, 5, Minor) => 0.5,
+        (true, 0 | 5, Minor) | (true, 3 | 8 | 10, Major) | (true, 2, Diminished) => 1.0,
+        // The major dominant of the harmonic minor scale.
+        (true, 7, Major) => 1.0,
+        (true, 7, Minor) | (true, 11, Diminished) => 0.75,
+        // The major fourth of the dorian mode.
+        (true, 5, Major) => 0.5,
+        _ => 0.0,
+    }
+}
+
+fn is_tonic_chord(key: &Key, chord: &Chord) -> bool {
+    chord.root.semitone() == key.tonic.semitone()
+        && (chord.quality() == Quality::Minor) == key.minor
+        && matches!(chord.quality(), Quality::Major | Quality::Minor)
+}
+
+fn all_keys() -> impl Iterator<Item = Key> {
+    (0..12u8).flat_map(|tonic| {
+        [false, true].map(move |minor| Key {
+            tonic: Note::from_semitone(tonic, crate::chord::is_flat_key(tonic, minor)),
+            minor,
+        })
+    })
+}
+
+/// The most probable key of a song, or `None` if it has no chords.
+pub fn detect_key(song: &ParsedSong) -> Option<(Key, f64)> {
+    let chords: Vec<Chord> = song.chords().filter_map(|a| a.chord()).collect();
+    let (first, last) = (chords.first()?, chords.last()?);
+    let total = chords.len() as f64 * (1.0 + TONIC_EMPHASIS) + 2.0 * TONIC_AT_START_OR_END;
+
+    let mut scores: Vec<(Key, f64)> = all_keys()
+        .map(|key| {
+            let mut score: f64 = chords
+                .iter()
+                .map(|chord| {
+                    let tonic = if is_tonic_chord(&key, chord) {
+                        TONIC_EMPHASIS
+                    } else {
+                        0.0
+                    };
+                    fit(&key, chord) + tonic
+                })
+                .sum();
+            for chord in [first, last] {
+                if is_tonic_chord(&key, chord) {
+                    score += TONIC_AT_START_OR_END;
+                }
+            }
+            (key, score / total)
+        })
+        .collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    // The share the best key would have if the scores were probabilities that grow steeply with
+    // how well a key fits.
+    let weights: Vec<f64> = scores
+        .iter()
+        .map(|(_, score)| (SHARPNESS * score).exp())
+        .collect();
+    let confidence = weights[0] / weights.iter().sum::<f64>();
+    Some((scores[0].0, confidence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detected(text: &str) -> Option<String> {
+        detect_key(&ParsedSong::from_text(text)).map(|(key, _)| key.to_string())
+    }
+
+    #[test]
+    fn the_key_is_found_from_the_chords() {
+        assert_eq!(
+            detected("G  Em  C  D\nla la la\nG\nla").as_deref(),
+            Some("G")
+        );
+        assert_eq!(
+            detected("Am  F  C  G\nla la la\nAm\nla").as_deref(),
+            Some("Am")
+        );
+        assert_eq!(
+            detected("Bb  Gm  Eb  F\nla la la\nBb\nla").as_deref(),
+            Some("Bb")
+        );
+    }
+
+    #[test]
+    fn the_harmonic_minor_dominant_fits_minor_keys() {
+        assert_eq!(detected("Dm  Gm  A7  Dm\nla la la").as_deref(), Some("Dm"));
+    }
+
+    #[test]
+    fn songs_without_chords_have_no_key() {
+        assert_eq!(detected("Only words\nand more words"), None);
+    }
+
+    #[test]
+    fn confidence_is_lower_when_other_keys_fit_as_well() {
+        let confidence = |text| detect_key(&ParsedSong::from_text(text)).unwrap().1;
+        let clear = confidence("C  Am  F  G  C\nla la la");
+        let vague = confidence("C  G\nla la");
+        assert!(clear > vague, "{clear} <= {vague}");
+        assert!((0.0..=1.0).contains(&vague));
+    }
+}
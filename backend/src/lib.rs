@@ -1,7 +1,30 @@
 pub mod arguments;
+pub mod auth;
+pub mod capo;
+pub mod chord;
+pub mod chord_diagram;
+pub mod chordpro;
 pub mod database_connection;
+pub mod error;
+pub mod html;
+pub mod key_detection;
+pub mod pdf;
 pub mod ql_mutation;
 pub mod ql_query;
+pub mod ql_subscription;
+pub mod setlist;
 pub mod song;
+pub mod song_access;
+pub mod song_connection;
+pub mod song_content;
+pub mod song_diff;
+pub mod song_events;
+pub mod song_input;
+pub mod song_repository;
+pub mod song_revision;
+pub mod song_search;
+pub mod songbook;
 pub mod spotify;
 pub mod spotify_track;
+pub mod tag;
+pub mod validation;
@@ -1,29 +1,42 @@
 use axum::body::{Body, Bytes};
-use axum::extract::Query;
-use axum::http::{Request, StatusCode};
-use axum::response::Redirect;
+use axum::extract::{Path, Query};
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::from_fn;
+use axum::response::{IntoResponse, Redirect, Response};
 use axum::routing::MethodFilter;
 use axum::{body, response::Html, routing::get, Extension, Json, Router};
 use chordmate::arguments::ChordmateArgs;
+use chordmate::auth::{self, Context};
+use chordmate::chord::Chord;
+use chordmate::chord_diagram::{ChordDiagram, Instrument};
 use chordmate::database_connection::DatabaseConnection;
+use chordmate::error::ChordmateError;
 use chordmate::ql_mutation::QLMutation;
 use chordmate::ql_query::QLQuery;
+use chordmate::ql_subscription::QLSubscription;
+use chordmate::song::Song;
+use chordmate::song_events::SongEvents;
+use chordmate::song_repository::{PostgresSongRepository, SongRepository};
+use chordmate::songbook::{self, SongbookOptions};
 use chordmate::spotify::{SpotifyClient, TokenError};
 use clap::Parser;
 use deadpool_postgres::Pool;
 use dotenvy::dotenv;
-use juniper::{EmptySubscription, RootNode};
-use juniper_axum::{graphiql, graphql, playground, ws};
+use juniper::{RootNode, ScalarValue};
+use juniper_axum::extract::JuniperRequest;
+use juniper_axum::response::JuniperResponse;
+use juniper_axum::{graphiql, playground, ws};
 use juniper_graphql_ws::ConnectionConfig;
 use log::info;
 use simple_logger::SimpleLogger;
 use std::collections::HashMap;
+use std::convert::Infallible;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::fs::ServeDir;
 
-type Schema = RootNode<QLQuery, QLMutation, EmptySubscription>;
+type Schema = RootNode<QLQuery, QLMutation, QLSubscription>;
 async fn homepage() -> Html<&'static str> {
     "<html><h1>juniper_axum/simple example</h1>\
            <div>visit <a href=\"/graphiql\">GraphiQL</a></div>\
@@ -46,6 +59,31 @@ async fn log_requests(
     next.run(req).await
 }
 
+async fn graphql(
+    Extension(schema): Extension<Arc<Schema>>,
+    Extension(context): Extension<Context>,
+    JuniperRequest(request): JuniperRequest,
+) -> JuniperResponse {
+    JuniperResponse(request.execute(&schema, &context).await)
+}
+
+/// Websocket connections cannot send headers from the browser, so subscriptions authenticate
+/// with an `authToken` in the parameters of the connection init message.
+async fn subscription_context(
+    database_connection: DatabaseConnection,
+    songs: Arc<dyn SongRepository>,
+    params: juniper::Variables,
+) -> Result<ConnectionConfig<Context>, Infallible> {
+    let token = params
+        .get("authToken")
+        .and_then(|token| token.as_scalar()?.try_as_str());
+    let context = match token {
+        Some(token) => auth::authenticate(&database_connection, songs, token).await,
+        None => Context::anonymous(database_connection, songs),
+    };
+    Ok(ConnectionConfig::new(context))
+}
+
 async fn spotify_callback(
     query: Query<HashMap<String, String>>,
     Extension(spotify_client): Extension<Arc<SpotifyClient>>,
@@ -72,7 +110,153 @@ async fn spotify_callback(
 
     Ok(Redirect::to(state))
 }
-fn router(query: QLQuery, mutation: QLMutation, spotify_client: Arc<SpotifyClient>) -> Router {
+/// Links to files cannot send an `Authorization` header, so downloads may instead authenticate
+/// with a `token` from the `createDownloadToken` mutation in the query string.
+async fn download_context(context: Context, query: &HashMap<String, String>) -> Context {
+    match query.get("token") {
+        Some(token) if context.user.is_none() => {
+            auth::authenticate_download(&context.database_connection, context.songs, token).await
+        }
+        _ => context,
+    }
+}
+
+/// Loads the songs with the given ids that the user of `context` may see.
+async fn load_songs(
+    context: &Context,
+    ids: &[i32],
+) -> Result<Vec<Song>, (StatusCode, &'static str)> {
+    let mut songs = context
+        .songs
+        .find_visible(ids, context.user_id())
+        .await
+        .map_err(|error| match error {
+            ChordmateError::DbUnavailable(_) => {
+                (StatusCode::SERVICE_UNAVAILABLE, "Database unavailable.")
+            }
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to query songs."),
+        })?;
+    // Keep the order the songs were requested in, e.g. the running order of a setlist.
+    songs.sort_by_key(|song| ids.iter().position(|&id| id == song.id));
+    if songs.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "Song not found."));
+    }
+    Ok(songs)
+}
+
+/// Renders songs as a PDF, with chord diagrams for the instrument given by `diagrams`, e.g.
+/// `?diagrams=ukulele`, or for the guitar if it is left empty.
+async fn pdf_response(
+    songs: Vec<Song>,
+    query: &HashMap<String, String>,
+) -> Result<Response, (StatusCode, &'static str)> {
+    let chord_diagrams = match query.get("diagrams").map(String::as_str) {
+        None => None,
+        Some("") => Some(Instrument::Guitar),
+        Some(name) => {
+            Some(Instrument::parse(name).ok_or((StatusCode::BAD_REQUEST, "Unknown instrument"))?)
+        }
+    };
+    // Finding the voicings for the chord diagrams takes a while, so the PDF is rendered on a
+    // blocking thread.
+    let pdf = tokio::task::spawn_blocking(move || {
+        songbook::render(&songs, &SongbookOptions { chord_diagrams })
+    })
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to render the PDF.",
+        )
+    })?;
+    Ok(([(header::CONTENT_TYPE, "application/pdf")], pdf).into_response())
+}
+
+/// Serves a song as a file, `/songs/42.cho` for its ChordPro export or `/songs/42.pdf` for a
+/// printable version. Songs that are not public need a download token, `?token=...`.
+async fn song_file(
+    Path(file): Path<String>,
+    query: Query<HashMap<String, String>>,
+    Extension(context): Extension<Context>,
+) -> Result<Response, (StatusCode, &'static str)> {
+    let context = download_context(context, &query).await;
+    let (id, extension) = file
+        .rsplit_once('.')
+        .and_then(|(id, extension)| Some((id.parse::<i32>().ok()?, extension)))
+        .ok_or((StatusCode::NOT_FOUND, "Unknown song file."))?;
+    let songs = load_songs(&context, &[id]).await?;
+    match extension {
+        "cho" => Ok((
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            songs[0].to_chord_pro(),
+        )
+            .into_response()),
+        "pdf" => pdf_response(songs, &query).await,
+        _ => Err((StatusCode::NOT_FOUND, "Unknown song file.")),
+    }
+}
+
+/// The most songs a songbook may hold, so that a single request cannot keep the server busy for
+/// long.
+const MAX_SONGBOOK_SONGS: usize = 200;
+
+/// Renders several songs into one PDF, e.g. `/songbook.pdf?ids=3,1,2&diagrams&token=...`.
+async fn songbook_pdf(
+    query: Query<HashMap<String, String>>,
+    Extension(context): Extension<Context>,
+) -> Result<Response, (StatusCode, &'static str)> {
+    let context = download_context(context, &query).await;
+    let ids = query
+        .get("ids")
+        .ok_or((StatusCode::BAD_REQUEST, "Missing ids"))?
+        .split(',')
+        .map(|id| id.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid ids"))?;
+    if ids.len() > MAX_SONGBOOK_SONGS {
+        return Err((StatusCode::BAD_REQUEST, "Too many songs for one songbook."));
+    }
+    let songs = load_songs(&context, &ids).await?;
+    pdf_response(songs, &query).await
+}
+
+/// Serves the diagram of a chord as an image, e.g. `/chords/Am7.svg?instrument=ukulele`.
+async fn chord_svg(
+    Path(file): Path<String>,
+    query: Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let chord = file
+        .strip_suffix(".svg")
+        .and_then(Chord::parse)
+        .ok_or((StatusCode::NOT_FOUND, "Unknown chord."))?;
+    let instrument = match query.get("instrument") {
+        Some(name) => {
+            Instrument::parse(name).ok_or((StatusCode::BAD_REQUEST, "Unknown instrument"))?
+        }
+        None => Instrument::Guitar,
+    };
+    let svg = tokio::task::spawn_blocking(move || {
+        ChordDiagram::new(&chord, instrument).map(|diagram| diagram.to_svg())
+    })
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to draw the diagram.",
+        )
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "No fingering found for this chord."))?;
+    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], svg))
+}
+
+fn router(
+    query: QLQuery,
+    mutation: QLMutation,
+    subscription: QLSubscription,
+    spotify_client: Arc<SpotifyClient>,
+    database_connection: DatabaseConnection,
+    songs: Arc<dyn SongRepository>,
+) -> Router {
     // During development, we want to use the frontend served by `npm start`.
     // That's faster development cycles than `npm run build; cargo run`.
     // However, we need to allow CORS to make it work.
@@ -82,19 +266,23 @@ fn router(query: QLQuery, mutation: QLMutation, spotify_client: Arc<SpotifyClien
         .allow_methods(Any)
         .allow_headers(Any);
 
-    let schema = Schema::new(query, mutation, EmptySubscription::new());
+    let schema = Schema::new(query, mutation, subscription);
     Router::new()
         .nest_service("/static", ServeDir::new("../frontend/build/static"))
         .route(
             "/graphql",
             axum::routing::on(
                 MethodFilter::GET.or(MethodFilter::POST),
-                graphql::<Arc<Schema>>,
+                graphql,
             ),
         )
         .route(
             "/subscriptions",
-            get(ws::<Arc<Schema>>(ConnectionConfig::new(()))),
+            get(ws::<Arc<Schema>>({
+                let database_connection = database_connection.clone();
+                let songs = songs.clone();
+                move |params| subscription_context(database_connection, songs, params)
+            })),
         )
         .route("/graphiql", get(graphiql("/graphql", "/subscriptions")))
         .route("/playground", get(playground("/graphql", "/subscriptions")))
@@ -113,10 +301,16 @@ fn router(query: QLQuery, mutation: QLMutation, spotify_client: Arc<SpotifyClien
                 }
             }),
         )
+        .route("/songs/{file}", get(song_file))
+        .route("/songbook.pdf", get(songbook_pdf))
+        .route("/chords/{file}", get(chord_svg))
         .route("/", get(homepage))
+        .layer(from_fn(auth::auth_layer))
         .layer(cors)
         .layer(Extension(Arc::new(schema)))
         .layer(Extension(spotify_client.clone()))
+        .layer(Extension(database_connection))
+        .layer(Extension(songs))
     // .layer(from_fn(log_requests))
 }
 
@@ -126,23 +320,32 @@ async fn serve(database_connection_pool: Pool, port: u16) {
         .expect("Failed to start TCP listener.");
 
     println!("listening on http://{}", listener.local_addr().unwrap());
-    let spotify_client = Arc::new(SpotifyClient::new());
+    let songs = Arc::new(PostgresSongRepository::new(
+        database_connection_pool.clone(),
+    ));
+    match songs.index_missing_lyrics().await {
+        Ok(0) => {}
+        Ok(count) => info!("Indexed the lyrics of {count} songs for searching."),
+        Err(error) => eprintln!("Error: failed to index lyrics: {error}"),
+    }
+    let spotify_client = Arc::new(SpotifyClient::from_env());
+    let song_events = SongEvents::new();
 
     axum::serve(
         listener,
         router(
             QLQuery {
-                database_connection: DatabaseConnection {
-                    connection_pool: database_connection_pool.clone(),
-                },
+                database_connection: DatabaseConnection::new(database_connection_pool.clone()),
                 spotify_client: spotify_client.clone(),
             },
             QLMutation {
-                database_connection: DatabaseConnection {
-                    connection_pool: database_connection_pool.clone(),
-                },
+                database_connection: DatabaseConnection::new(database_connection_pool.clone()),
+                song_events: song_events.clone(),
             },
+            QLSubscription { song_events },
             spotify_client,
+            DatabaseConnection::new(database_connection_pool),
+            songs,
         ),
     )
     .await
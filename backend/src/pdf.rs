@@ -0,0 +1,306 @@
+//! A small writer for PDF documents made of text and simple vector graphics.
+//!
+//! Only the standard fonts every PDF viewer ships with are used, so no fonts have to be embedded.
+
+use std::fmt::Write;
+
+pub const A4_WIDTH: f32 = 595.0;
+pub const A4_HEIGHT: f32 = 842.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Font {
+    Courier,
+    CourierBold,
+    Helvetica,
+    HelveticaBold,
+}
+
+impl Font {
+    const ALL: [Font; 4] = [
+        Font::Courier,
+        Font::CourierBold,
+        Font::Helvetica,
+        Font::HelveticaBold,
+    ];
+
+    fn base_font(self) -> &'static str {
+        match self {
+            Font::Courier => "Courier",
+            Font::CourierBold => "Courier-Bold",
+            Font::Helvetica => "Helvetica",
+            Font::HelveticaBold => "Helvetica-Bold",
+        }
+    }
+
+    fn resource_name(self) -> String {
+        format!(
+            "F{}",
+            Font::ALL.iter().position(|&f| f == self).unwrap() + 1
+        )
+    }
+
+    /// The width of a character relative to the font size. Exact for the monospaced fonts, an
+    /// average for the others.
+    pub fn char_width(self) -> f32 {
+        match self {
+            Font::Courier | Font::CourierBold => 0.6,
+            Font::Helvetica | Font::HelveticaBold => 0.55,
+        }
+    }
+}
+
+/// Encodes `text` as a PDF string literal in WinAnsiEncoding, the encoding of the standard fonts.
+fn encode_text(text: &str) -> Vec<u8> {
+    let mut encoded = vec![b'('];
+    for c in text.chars() {
+        let byte = match c {
+            '(' | ')' | '\\' => {
+                encoded.push(b'\\');
+                c as u8
+            }
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u32 as u8,
+            '€' => 0x80,
+            '‚' => 0x82,
+            '„' => 0x84,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '–' => 0x96,
+            '—' => 0x97,
+            '♯' => b'#',
+            '♭' => b'b',
+            _ => b'?',
+        };
+        encoded.push(byte);
+    }
+    encoded.push(b')');
+    encoded
+}
+
+/// A page, with coordinates in points measured from the bottom left corner.
+#[derive(Default)]
+pub struct Page {
+    content: Vec<u8>,
+}
+
+impl Page {
+    fn push(&mut self, operators: String) {
+        self.content.extend(operators.into_bytes());
+    }
+
+    pub fn text(&mut self, x: f32, y: f32, font: Font, size: f32, text: &str) {
+        self.push(format!(
+            "BT /{} {size:.1} Tf {x:.2} {y:.2} Td ",
+            font.resource_name()
+        ));
+        self.content.extend(encode_text(text));
+        self.push(String::from(" Tj ET\n"));
+    }
+
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32) {
+        self.push(format!(
+            "{width:.2} w {:.2} {:.2} m {:.2} {:.2} l S\n",
+            from.0, from.1, to.0, to.1
+        ));
+    }
+
+    /// Draws a rectangle with its bottom left corner at `x`, `y`, filled with black or white.
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, filled: bool) {
+        let gray = if filled { 0 } else { 1 };
+        self.push(format!(
+            "0.5 w {gray} g {x:.2} {y:.2} {width:.2} {height:.2} re B 0 g\n"
+        ));
+    }
+
+    pub fn circle(&mut self, x: f32, y: f32, radius: f32, filled: bool) {
+        // Four cubic Bézier curves approximate a circle closely enough.
+        let k = radius * 0.5523;
+        let mut path = format!("0.8 w {:.2} {y:.2} m ", x + radius);
+        for (dx1, dy1, dx2, dy2, dx, dy) in [
+            (radius, k, k, radius, 0.0, radius),
+            (-k, radius, -radius, k, -radius, 0.0),
+            (-radius, -k, -k, -radius, 0.0, -radius),
+            (k, -radius, radius, -k, radius, 0.0),
+        ] {
+            let _ = write!(
+                path,
+                "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c ",
+                x + dx1,
+                y + dy1,
+                x + dx2,
+                y + dy2,
+                x + dx,
+                y + dy
+            );
+        }
+        path.push_str(if filled { "f\n" } else { "S\n" });
+        self.push(path);
+    }
+}
+
+#[derive(Default)]
+pub struct PdfDocument {
+    pages: Vec<Page>,
+}
+
+impl PdfDocument {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_page(&mut self) -> &mut Page {
+        self.pages.push(Page::default());
+        self.pages.last_mut().unwrap()
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn page_mut(&mut self, index: usize) -> Option<&mut Page> {
+        self.pages.get_mut(index)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Object numbers: 1 catalog, 2 page tree, then the fonts, then a page and its content
+        // stream for every page.
+        let first_page = 3 + Font::ALL.len();
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        let kids: Vec<String> = (0..self.pages.len())
+            .map(|i| format!("{} 0 R", first_page + 2 * i))
+            .collect();
+        objects.push(
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                self.pages.len()
+            )
+            .into_bytes(),
+        );
+        for font in Font::ALL {
+            objects.push(
+                format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                    font.base_font()
+                )
+                .into_bytes(),
+            );
+        }
+        let fonts: Vec<String> = Font::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, font)| format!("/{} {} 0 R", font.resource_name(), 3 + i))
+            .collect();
+        for (i, page) in self.pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {A4_WIDTH} {A4_HEIGHT}] \
+                     /Resources << /Font << {} >> >> /Contents {} 0 R >>",
+                    fonts.join(" "),
+                    first_page + 2 * i + 1
+                )
+                .into_bytes(),
+            );
+            let mut stream = format!("<< /Length {} >>\nstream\n", page.content.len()).into_bytes();
+            stream.extend(&page.content);
+            stream.extend(b"\nendstream");
+            objects.push(stream);
+        }
+
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+            pdf.extend(object);
+            pdf.extend(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(trailer, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            trailer,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        );
+        pdf.extend(trailer.into_bytes());
+        pdf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(pages: usize) -> Vec<u8> {
+        let mut document = PdfDocument::new();
+        for i in 0..pages {
+            let page = document.add_page();
+            page.text(50.0, 50.0, Font::Helvetica, 10.0, &format!("Page (è) {i}"));
+            page.circle(100.0, 100.0, 3.0, true);
+        }
+        document.to_bytes()
+    }
+
+    /// Where `needle` last occurs in `pdf`, as a byte offset.
+    fn rfind(pdf: &[u8], needle: &str) -> usize {
+        pdf.windows(needle.len())
+            .rposition(|window| window == needle.as_bytes())
+            .unwrap()
+    }
+
+    /// The number after the last occurrence of `keyword` in `pdf`.
+    fn number_after(pdf: &[u8], keyword: &str) -> usize {
+        let start = rfind(pdf, keyword) + keyword.len();
+        let digits: String = pdf[start..]
+            .iter()
+            .map(|&byte| byte as char)
+            .skip_while(char::is_ascii_whitespace)
+            .take_while(char::is_ascii_digit)
+            .collect();
+        digits.parse().unwrap()
+    }
+
+    #[test]
+    fn the_cross_reference_table_points_at_every_object() {
+        let pdf = document(2);
+        let xref = number_after(&pdf, "startxref");
+        assert_eq!(xref, rfind(&pdf, "xref\n0 "));
+        let table = String::from_utf8(pdf[xref..].to_vec()).unwrap();
+        assert!(table.starts_with("xref\n0 11\n0000000000 65535 f \n"));
+        let offsets: Vec<usize> = table
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        assert_eq!(offsets.len(), 10);
+        for (i, offset) in offsets.into_iter().enumerate() {
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", i + 1).as_bytes()));
+        }
+        assert!(table.contains("/Size 11 /Root 1 0 R"));
+    }
+
+    #[test]
+    fn stream_lengths_match_the_content() {
+        let pdf = document(1);
+        let length = number_after(&pdf, "/Length");
+        let start = rfind(&pdf, ">>\nstream\n") + ">>\nstream\n".len();
+        assert!(pdf[start + length..].starts_with(b"\nendstream"));
+    }
+
+    #[test]
+    fn text_is_escaped_and_encoded_for_the_standard_fonts() {
+        assert_eq!(encode_text("a(b)\\"), b"(a\\(b\\)\\\\)");
+        assert_eq!(
+            encode_text("è–F♯♭"),
+            [b'(', 0xe8, 0x96, b'F', b'#', b'b', b')']
+        );
+        assert_eq!(encode_text("日"), b"(?)");
+    }
+}
@@ -1,69 +1,551 @@
+use crate::auth::{self, Context};
+use crate::chord::Key;
+use crate::chordpro;
 use crate::database_connection::DatabaseConnection;
-use juniper::{graphql_object, FieldResult};
+use crate::error::{ChordmateError, ChordmateResult};
+use crate::html;
+use crate::setlist;
+use crate::song_access::{self, SongRole};
+use crate::song_content::{self, ParsedSong};
+use crate::song_events::{SongChangeKind, SongEvents};
+use crate::song_input::SongInput;
+use crate::song_repository::SongUpdate;
+use crate::tag;
+use crate::validation::{self, Validator, MAX_CONTENT_LENGTH, MAX_NAME_LENGTH};
+use juniper::graphql_object;
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+/// Hashing very long passwords would keep the server busy for nothing.
+const MAX_PASSWORD_LENGTH: usize = 1024;
+const MAX_USERNAME_LENGTH: usize = 50;
+const MAX_NOTES_LENGTH: usize = 2000;
 
 pub struct QLMutation {
     pub database_connection: DatabaseConnection,
+    pub song_events: SongEvents,
 }
 
-#[graphql_object]
+#[graphql_object(context = Context)]
 impl QLMutation {
-    async fn add_song(&self) -> FieldResult<i32> {
+    /// Creates an account and logs it in, returning a bearer token for the new session.
+    async fn register(&self, username: String, password: String) -> ChordmateResult<String> {
+        let username = username.trim();
+        let mut validator = Validator::new();
+        validator.not_blank("username", username);
+        validator.max_length("username", username, MAX_USERNAME_LENGTH);
+        if password.chars().count() < MIN_PASSWORD_LENGTH {
+            validator.invalid(
+                "password",
+                format!("The password must have at least {MIN_PASSWORD_LENGTH} characters."),
+            );
+        }
+        validator.max_length("password", &password, MAX_PASSWORD_LENGTH);
+        validator.finish()?;
+        let password_hash = auth::hash_password(password).await?;
         let client = self.database_connection.get().await?;
         let statement = client
             .prepare(
-                "INSERT INTO songs (title, artist, content, spotify_track) VALUES ('', '', '', '') RETURNING id"
+                "INSERT INTO users (username, password_hash) VALUES ($1, $2) \
+                 ON CONFLICT (username) DO NOTHING RETURNING id;",
             )
+            .await?;
+        let Some(row) = client
+            .query_opt(&statement, &[&username, &password_hash])
+            .await?
+        else {
+            return Err(ChordmateError::UsernameTaken(format!(
+                "The username '{username}' is taken."
+            )));
+        };
+        self.start_session(row.try_get("id")?).await
+    }
+
+    /// Returns a bearer token to send as `Authorization: Bearer <token>` with later requests.
+    async fn login(&self, username: String, password: String) -> ChordmateResult<String> {
+        let client = self.database_connection.get().await?;
+        let statement = client
+            .prepare("SELECT id, password_hash FROM users WHERE username = $1;")
+            .await?;
+        let row = client.query_opt(&statement, &[&username.trim()]).await?;
+        let password_hash = row
+            .as_ref()
+            .map(|row| row.try_get("password_hash"))
+            .transpose()?;
+        let is_valid = auth::verify_password(password, password_hash).await?;
+        match row {
+            Some(row) if is_valid => self.start_session(row.try_get("id")?).await,
+            _ => Err(ChordmateError::Unauthenticated(String::from(
+                "Wrong username or password.",
+            ))),
+        }
+    }
+
+    /// Ends the session the request was made with.
+    async fn logout(&self, context: &Context) -> ChordmateResult<bool> {
+        let Some(token) = &context.session_token else {
+            return Ok(false);
+        };
+        let client = self.database_connection.get().await?;
+        let statement = client
+            .prepare("DELETE FROM sessions WHERE token_hash = $1;")
+            .await?;
+        Ok(client
+            .execute(&statement, &[&auth::token_hash(token)])
+            .await?
+            > 0)
+    }
+
+    /// Returns a token that authenticates downloads of song files and songbooks for a few
+    /// minutes, sent as `?token=<token>` where links cannot send an `Authorization` header.
+    async fn create_download_token(&self, context: &Context) -> ChordmateResult<String> {
+        let user = context.user()?;
+        let token = auth::new_session_token();
+        let client = self.database_connection.get().await?;
+        let statement = client
+            .prepare("DELETE FROM download_tokens WHERE expires_at <= now();")
+            .await?;
+        client.execute(&statement, &[]).await?;
+        let statement = client
+            .prepare(
+                "INSERT INTO download_tokens (token_hash, user_id, expires_at) \
+                 VALUES ($1, $2, now() + make_interval(mins => $3));",
+            )
+            .await?;
+        client
+            .execute(
+                &statement,
+                &[
+                    &auth::token_hash(&token),
+                    &user.id,
+                    &auth::DOWNLOAD_TOKEN_MINUTES,
+                ],
+            )
+            .await?;
+        Ok(token)
+    }
+
+    async fn add_song(&self, context: &Context) -> ChordmateResult<i32> {
+        let user = context.user()?;
+        let id = context.songs.create(user.id, "", "", "").await?;
+        self.song_events.publish(id, SongChangeKind::Created);
+        Ok(id)
+    }
+
+    /// Creates a song from a ChordPro document, taking title and artist from its directives.
+    async fn import_chord_pro(&self, context: &Context, text: String) -> ChordmateResult<i32> {
+        let mut validator = Validator::new();
+        validator.max_length("text", &text, MAX_CONTENT_LENGTH);
+        validator.finish()?;
+        context.user()?;
+        let song = chordpro::parse(&text);
+        self.insert_song(
+            context,
+            &song.title.unwrap_or_default(),
+            &song.artist.unwrap_or_default(),
+            &song.content,
+        )
+        .await
+    }
+
+    /// Creates a song from plain text with the chords written on their own lines above the lyrics.
+    async fn import_chord_sheet(
+        &self,
+        context: &Context,
+        text: String,
+        title: String,
+        artist: String,
+    ) -> ChordmateResult<i32> {
+        let mut validator = Validator::new();
+        validator.max_length("text", &text, MAX_CONTENT_LENGTH);
+        validator.max_length("title", &title, MAX_NAME_LENGTH);
+        validator.max_length("artist", &artist, MAX_NAME_LENGTH);
+        validator.finish()?;
+        self.insert_song(context, &title, &artist, &ParsedSong::from_text(&text))
             .await
-            .expect("SQL query preparation failed.");
+    }
 
-        let row = client
-            .query_one(&statement, &[])
+    /// Returns whether the song was deleted. A song that does not exist is `NOT_FOUND`, `false`
+    /// only happens if another request deleted it at the same time.
+    async fn delete_song(&self, context: &Context, id: i32) -> ChordmateResult<bool> {
+        song_access::require_role(context, id, SongRole::Owner).await?;
+        let audience = context.songs.audience(id).await?;
+        let deleted = context.songs.delete(id).await?;
+        if let Some(audience) = audience.filter(|_| deleted) {
+            self.song_events.publish_deleted(id, audience);
+        }
+        Ok(deleted)
+    }
+
+    async fn update_song_content(
+        &self,
+        context: &Context,
+        id: i32,
+        content: String,
+    ) -> ChordmateResult<i32> {
+        let mut validator = Validator::new();
+        validator.content("content", &content);
+        validator.finish()?;
+        song_access::require_role(context, id, SongRole::Editor).await?;
+        self.save_content(context, id, &content, &SongUpdate::default())
             .await
-            .expect("SQL query failed");
+    }
 
-        let id: i32 = row.try_get("id")?;
+    /// Changes the details of a song, everything but its content.
+    async fn update_song(
+        &self,
+        context: &Context,
+        id: i32,
+        input: SongInput,
+    ) -> ChordmateResult<i32> {
+        let update = input.into_update()?;
+        song_access::require_role(context, id, SongRole::Editor).await?;
+        if update.is_empty() {
+            return Ok(id);
+        }
+        if !context.songs.update(id, &update).await? {
+            return Err(song_not_found(id));
+        }
+        self.song_events.publish(id, SongChangeKind::Updated);
         Ok(id)
     }
 
-    async fn delete_song(&self, id: i32) -> FieldResult<bool> {
+    async fn transpose_song(
+        &self,
+        context: &Context,
+        id: i32,
+        semitones: i32,
+    ) -> ChordmateResult<i32> {
+        let mut validator = Validator::new();
+        validator.range("semitones", semitones, -11, 11);
+        validator.finish()?;
+        song_access::require_role(context, id, SongRole::Editor).await?;
+        let song = context
+            .songs
+            .find(id)
+            .await?
+            .ok_or_else(|| song_not_found(id))?;
+        // A key set on the song moves along with its chords.
+        let key = song.stored_key().map(|key| key.transpose(semitones));
+        let content = song_content::transpose_html(
+            song.loaded_content(),
+            semitones,
+            key.map(|key| key.prefers_flats()),
+        );
+        let update = SongUpdate {
+            key: key.map(|key| Some(key.to_string())),
+            ..SongUpdate::default()
+        };
+        self.save_content(context, id, &content, &update).await
+    }
+
+    /// Puts the content of an earlier revision back. The restored content is saved as a new
+    /// revision, so the revisions in between are kept.
+    async fn restore_song_revision(
+        &self,
+        context: &Context,
+        song_id: i32,
+        revision_id: i32,
+    ) -> ChordmateResult<i32> {
+        song_access::require_role(context, song_id, SongRole::Editor).await?;
+        let Some(revision) = context.songs.revision(song_id, revision_id).await? else {
+            return Err(ChordmateError::NotFound(format!(
+                "Song {song_id} has no revision {revision_id}."
+            )));
+        };
+        self.save_content(context, song_id, &revision.content, &SongUpdate::default())
+            .await
+    }
+
+    /// Tags a song, creating the tag if nobody used it before.
+    async fn add_tag(&self, context: &Context, song_id: i32, name: String) -> ChordmateResult<i32> {
+        let name = tag::normalize_name(&name)?;
+        song_access::require_role(context, song_id, SongRole::Editor).await?;
+        context.songs.add_tag(song_id, name).await?;
+        self.song_events.publish(song_id, SongChangeKind::Updated);
+        Ok(song_id)
+    }
+
+    /// Removes a tag from a song. Returns whether the song had the tag.
+    async fn remove_tag(
+        &self,
+        context: &Context,
+        song_id: i32,
+        name: String,
+    ) -> ChordmateResult<bool> {
+        song_access::require_role(context, song_id, SongRole::Editor).await?;
+        let removed = context.songs.remove_tag(song_id, name.trim()).await?;
+        if removed {
+            self.song_events.publish(song_id, SongChangeKind::Updated);
+        }
+        Ok(removed)
+    }
+
+    /// Gives another user access to a song, or changes the access they have.
+    async fn share_song(
+        &self,
+        context: &Context,
+        song_id: i32,
+        username: String,
+        role: SongRole,
+    ) -> ChordmateResult<i32> {
+        if role == SongRole::Owner {
+            return Err(validation::invalid(
+                "role",
+                "A song can only be shared with viewers and editors.",
+            ));
+        }
+        song_access::require_role(context, song_id, SongRole::Owner).await?;
+        let user_id = self.user_id(context, &username).await?;
+        context.songs.share(song_id, user_id, role).await?;
+        Ok(song_id)
+    }
+
+    async fn unshare_song(
+        &self,
+        context: &Context,
+        song_id: i32,
+        username: String,
+    ) -> ChordmateResult<bool> {
+        song_access::require_role(context, song_id, SongRole::Owner).await?;
+        let user_id = self.user_id(context, &username).await?;
+        let unshared = context.songs.unshare(song_id, user_id).await?;
+        if unshared {
+            // Lets the user's subscriptions to the song notice that they lost access.
+            self.song_events.publish(song_id, SongChangeKind::Updated);
+        }
+        Ok(unshared)
+    }
+
+    async fn create_setlist(&self, context: &Context, name: String) -> ChordmateResult<i32> {
+        let user = context.user()?;
+        let mut validator = Validator::new();
+        validator.not_blank("name", &name);
+        validator.max_length("name", &name, MAX_NAME_LENGTH);
+        validator.finish()?;
         let client = self.database_connection.get().await?;
         let statement = client
-            .prepare("DELETE FROM songs WHERE id = $1;")
-            .await
-            .expect("SQL query preparation failed.");
-        client.execute(&statement, &[&id]).await?;
-        Ok(true)
+            .prepare("INSERT INTO setlists (name, owner_id) VALUES ($1, $2) RETURNING id;")
+            .await?;
+        let row = client.query_one(&statement, &[&name, &user.id]).await?;
+        Ok(row.try_get("id")?)
     }
 
-    async fn update_song_content(&self, id: i32, content: String) -> FieldResult<i32> {
+    async fn rename_setlist(
+        &self,
+        context: &Context,
+        id: i32,
+        name: String,
+    ) -> ChordmateResult<i32> {
+        context.user()?;
+        let mut validator = Validator::new();
+        validator.not_blank("name", &name);
+        validator.max_length("name", &name, MAX_NAME_LENGTH);
+        validator.finish()?;
         let client = self.database_connection.get().await?;
+        setlist::require_owner(&client, context, id).await?;
         let statement = client
-            .prepare("UPDATE songs SET content = $2 WHERE id = $1 RETURNING id;")
-            .await
-            .expect("SQL query preparation failed.");
-        let row = client.query_one(&statement, &[&id, &content]).await?;
+            .prepare("UPDATE setlists SET name = $2 WHERE id = $1 RETURNING id;")
+            .await?;
+        let row = client
+            .query_opt(&statement, &[&id, &name])
+            .await?
+            .ok_or_else(|| setlist::setlist_not_found(id))?;
         Ok(row.try_get("id")?)
     }
 
-    async fn update_song_track(&self, id: i32, track: String) -> FieldResult<i32> {
+    /// Returns whether the setlist was deleted, `false` only if it was deleted at the same time
+    /// by another request.
+    async fn delete_setlist(&self, context: &Context, id: i32) -> ChordmateResult<bool> {
         let client = self.database_connection.get().await?;
+        setlist::require_owner(&client, context, id).await?;
         let statement = client
-            .prepare("UPDATE songs SET spotify_track = $2 WHERE id = $1 RETURNING id;")
-            .await
-            .expect("SQL query preparation failed.");
-        let row = client.query_one(&statement, &[&id, &track]).await?;
+            .prepare("DELETE FROM setlists WHERE id = $1;")
+            .await?;
+        Ok(client.execute(&statement, &[&id]).await? > 0)
+    }
+
+    /// Appends a song to the end of a setlist and returns the id of the new entry.
+    async fn add_song_to_setlist(
+        &self,
+        context: &Context,
+        setlist_id: i32,
+        song_id: i32,
+    ) -> ChordmateResult<i32> {
+        // Checked before taking a connection, as the song repository needs one of its own.
+        song_access::require_role(context, song_id, SongRole::Viewer).await?;
+        let mut client = self.database_connection.get().await?;
+        setlist::require_owner(&client, context, setlist_id).await?;
+        let transaction = client.transaction().await?;
+        setlist::lock(&transaction, setlist_id).await?;
+        let statement = transaction
+            .prepare(
+                "INSERT INTO setlist_entries (setlist_id, song_id, position) \
+                 SELECT $1, $2, COALESCE(MAX(position) + 1, 0) FROM setlist_entries WHERE setlist_id = $1 \
+                 RETURNING id;",
+            )
+            .await?;
+        let row = transaction
+            .query_one(&statement, &[&setlist_id, &song_id])
+            .await?;
+        transaction.commit().await?;
         Ok(row.try_get("id")?)
     }
 
-    async fn update_song_meta(&self, id: i32, title: String, artist: String) -> FieldResult<i32> {
+    /// Returns whether the entry was removed, `false` only if it was removed at the same time by
+    /// another request.
+    async fn remove_setlist_entry(
+        &self,
+        context: &Context,
+        entry_id: i32,
+    ) -> ChordmateResult<bool> {
         let client = self.database_connection.get().await?;
+        setlist::require_entry_owner(&client, context, entry_id).await?;
         let statement = client
-            .prepare("UPDATE songs SET title = $2, artist = $3 WHERE id = $1 RETURNING id;")
-            .await
-            .expect("SQL query preparation failed.");
-        let row = client
-            .query_one(&statement, &[&id, &title, &artist])
+            .prepare("DELETE FROM setlist_entries WHERE id = $1;")
+            .await?;
+        Ok(client.execute(&statement, &[&entry_id]).await? > 0)
+    }
+
+    /// Puts the entries of a setlist into the given order. `entry_ids` must list every entry of
+    /// the setlist exactly once.
+    async fn reorder_setlist(
+        &self,
+        context: &Context,
+        setlist_id: i32,
+        entry_ids: Vec<i32>,
+    ) -> ChordmateResult<i32> {
+        let mut client = self.database_connection.get().await?;
+        setlist::require_owner(&client, context, setlist_id).await?;
+        let transaction = client.transaction().await?;
+        setlist::lock(&transaction, setlist_id).await?;
+        let statement = transaction
+            .prepare("SELECT id FROM setlist_entries WHERE setlist_id = $1;")
+            .await?;
+        let current: Vec<i32> = transaction
+            .query(&statement, &[&setlist_id])
+            .await?
+            .iter()
+            .map(|row| row.try_get("id"))
+            .collect::<Result<_, _>>()?;
+        setlist::check_order(current, &entry_ids)?;
+
+        let statement = transaction
+            .prepare("UPDATE setlist_entries SET position = $2 WHERE id = $1;")
             .await?;
+        for (position, entry_id) in entry_ids.iter().enumerate() {
+            transaction
+                .execute(&statement, &[entry_id, &(position as i32)])
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(setlist_id)
+    }
+
+    /// Sets the key a song is played in at this gig, and notes for the band.
+    async fn update_setlist_entry(
+        &self,
+        context: &Context,
+        entry_id: i32,
+        key_override: Option<String>,
+        notes: String,
+    ) -> ChordmateResult<i32> {
+        context.user()?;
+        let mut validator = Validator::new();
+        // Stored the way song keys are, e.g. `Amin` as `Am`.
+        let key_override = key_override.and_then(|key| match Key::parse(&key) {
+            Some(key) => Some(key.to_string()),
+            None => {
+                validator.invalid("keyOverride", format!("'{key}' is not a key."));
+                None
+            }
+        });
+        validator.max_length("notes", &notes, MAX_NOTES_LENGTH);
+        validator.finish()?;
+        let client = self.database_connection.get().await?;
+        setlist::require_entry_owner(&client, context, entry_id).await?;
+        let statement = client
+            .prepare(
+                "UPDATE setlist_entries SET key_override = $2, notes = $3 WHERE id = $1 RETURNING id;",
+            )
+            .await?;
+        let row = client
+            .query_opt(&statement, &[&entry_id, &key_override, &notes])
+            .await?
+            .ok_or_else(|| {
+                ChordmateError::NotFound(format!("There is no setlist entry with id {entry_id}."))
+            })?;
         Ok(row.try_get("id")?)
     }
 }
+
+impl QLMutation {
+    async fn user_id(&self, context: &Context, username: &str) -> ChordmateResult<i32> {
+        match context.songs.user_named(username.trim()).await? {
+            Some(user) => Ok(user.id),
+            None => Err(ChordmateError::NotFound(format!(
+                "There is no user called '{}'.",
+                username.trim()
+            ))),
+        }
+    }
+
+    async fn start_session(&self, user_id: i32) -> ChordmateResult<String> {
+        let token = auth::new_session_token();
+        let client = self.database_connection.get().await?;
+        let statement = client
+            .prepare(
+                "INSERT INTO sessions (token_hash, user_id, expires_at) \
+                 VALUES ($1, $2, now() + make_interval(days => $3));",
+            )
+            .await?;
+        client
+            .execute(
+                &statement,
+                &[&auth::token_hash(&token), &user_id, &auth::SESSION_DAYS],
+            )
+            .await?;
+        Ok(token)
+    }
+
+    async fn insert_song(
+        &self,
+        context: &Context,
+        title: &str,
+        artist: &str,
+        content: &ParsedSong,
+    ) -> ChordmateResult<i32> {
+        let owner = context.user()?;
+        let id = context
+            .songs
+            .create(owner.id, title, artist, &content.to_html())
+            .await?;
+        self.song_events.publish(id, SongChangeKind::Created);
+        Ok(id)
+    }
+
+    /// Replaces the content of a song, stripped of any markup the editor does not produce, and
+    /// keeps the new content as a revision. The details in `update` are changed along with it.
+    async fn save_content(
+        &self,
+        context: &Context,
+        id: i32,
+        content: &str,
+        update: &SongUpdate,
+    ) -> ChordmateResult<i32> {
+        let content = html::sanitize(content);
+        if !context
+            .songs
+            .save_content(id, &content, update, context.user_id())
+            .await?
+        {
+            return Err(song_not_found(id));
+        }
+        self.song_events.publish(id, SongChangeKind::Updated);
+        Ok(id)
+    }
+}
+
+/// For songs that were deleted after their role was checked.
+fn song_not_found(id: i32) -> ChordmateError {
+    ChordmateError::NotFound(format!("There is no song with id {id}."))
+}
@@ -1,46 +1,155 @@
+use crate::auth::{Context, User};
+use crate::chord::Chord;
+use crate::chord_diagram::{ChordDiagram, Instrument};
 use crate::database_connection::DatabaseConnection;
+use crate::error::{ChordmateError, ChordmateResult};
+use crate::setlist::{self, Setlist};
 use crate::song::Song;
+use crate::song_access::{self, SongRole};
+use crate::song_connection::SongConnection;
+use crate::song_search::{self, SongFilter, SongOrder};
 use crate::spotify::SpotifyClient;
 use crate::spotify_track::SpotifyTrack;
-use juniper::{graphql_object, FieldError, FieldResult, Value};
+use crate::tag::Tag;
+use crate::validation;
+use juniper::graphql_object;
 use std::sync::Arc;
-use tokio_postgres::Row;
 
 pub struct QLQuery {
     pub database_connection: DatabaseConnection,
     pub spotify_client: Arc<SpotifyClient>,
 }
 
-#[graphql_object]
+#[graphql_object(context = Context)]
 impl QLQuery {
-    pub async fn songs(&self) -> FieldResult<Vec<Song>> {
-        let client = self.database_connection.get().await?;
-        let statement = client
-            .prepare("SELECT id, title, artist, spotify_track, content FROM songs")
-            .await
-            .expect("SQL query preparation failed");
+    /// The user making the request, if they are logged in.
+    fn me(context: &Context) -> Option<&User> {
+        context.user.as_ref()
+    }
 
-        let rows: Vec<Row> = client
-            .query(&statement, &[])
+    /// The songs the calling user may see, optionally only those matching `search` in their
+    /// title, artist or lyrics. Pages through them with `first` and the `after` cursor, which
+    /// takes the cursor of the last song of the previous page.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn songs(
+        &self,
+        context: &Context,
+        search: Option<String>,
+        artist: Option<String>,
+        tag: Option<String>,
+        key: Option<String>,
+        order_by: Option<SongOrder>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> ChordmateResult<Vec<Song>> {
+        let first = song_search::page_size(first)?;
+        let offset = song_search::offset_after(after.as_deref())?;
+        let filter = SongFilter {
+            search,
+            artist,
+            tag,
+            key,
+            order_by,
+        };
+        context
+            .songs
+            .search(&filter, context.user_id(), offset, first)
             .await
-            .expect("SQL query failed");
+    }
 
-        rows.iter()
-            .map(|row| {
-                Song::from_row(row).map_err(|e| {
-                    FieldError::new("Failed to parse song.", Value::scalar(e.to_string()))
-                })
-            })
-            .collect::<FieldResult<Vec<Song>>>()
+    /// Like `songs`, as a connection to page through lazily.
+    #[allow(clippy::too_many_arguments)]
+    async fn songs_connection(
+        context: &Context,
+        search: Option<String>,
+        artist: Option<String>,
+        tag: Option<String>,
+        key: Option<String>,
+        order_by: Option<SongOrder>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> ChordmateResult<SongConnection> {
+        let filter = SongFilter {
+            search,
+            artist,
+            tag,
+            key,
+            order_by,
+        };
+        SongConnection::load(
+            context,
+            filter,
+            song_search::page_size(first)?,
+            after.as_deref(),
+        )
+        .await
     }
-    async fn song(&self, id: i32) -> FieldResult<Song> {
+
+    async fn song(context: &Context, id: i32) -> ChordmateResult<Song> {
+        song_access::require_role(context, id, SongRole::Viewer).await?;
+        // The song may have been deleted since its role was looked up.
+        context
+            .songs
+            .find(id)
+            .await?
+            .ok_or_else(|| ChordmateError::NotFound(format!("There is no song with id {id}.")))
+    }
+
+    /// The tags of the songs the calling user may see, with how many songs have them.
+    async fn tags(context: &Context) -> ChordmateResult<Vec<Tag>> {
+        context.songs.tag_counts(context.user_id()).await
+    }
+
+    /// The setlists of the calling user, and those made before there were accounts.
+    async fn setlists(&self, context: &Context) -> ChordmateResult<Vec<Setlist>> {
         let client = self.database_connection.get().await?;
-        let statement = client.prepare("SELECT * FROM songs WHERE id = $1").await?;
-        let row = client.query_one(&statement, &[&id]).await?;
-        Ok(Song::from_row(&row)?)
+        let statement = client
+            .prepare(&format!(
+                "SELECT id, name FROM setlists WHERE {} ORDER BY name",
+                setlist::VISIBLE
+            ))
+            .await?;
+        let mut setlists = Vec::new();
+        for row in client.query(&statement, &[&context.user_id()]).await? {
+            setlists.push(Setlist::from_row(&client, &row, context.user_id()).await?);
+        }
+        Ok(setlists)
+    }
+
+    async fn setlist(&self, context: &Context, id: i32) -> ChordmateResult<Setlist> {
+        let client = self.database_connection.get().await?;
+        let statement = client
+            .prepare(&format!(
+                "SELECT id, name, {} AS visible FROM setlists WHERE id = $2",
+                setlist::VISIBLE
+            ))
+            .await?;
+        let row = client
+            .query_opt(&statement, &[&context.user_id(), &id])
+            .await?
+            .ok_or_else(|| setlist::setlist_not_found(id))?;
+        if !row.try_get::<_, bool>("visible")? {
+            context.user()?;
+            return Err(ChordmateError::Forbidden(format!(
+                "You need to be the owner of setlist {id}."
+            )));
+        }
+        Ok(Setlist::from_row(&client, &row, context.user_id()).await?)
+    }
+
+    /// How to play a chord, e.g. `Am7` or `D/F#`, on an instrument. Returns `null` for chords
+    /// there is no playable fingering for.
+    async fn chord_diagram(
+        name: String,
+        #[graphql(default = Instrument::Guitar)] instrument: Instrument,
+    ) -> ChordmateResult<Option<ChordDiagram>> {
+        let chord = Chord::parse(&name)
+            .ok_or_else(|| validation::invalid("name", format!("'{name}' is not a chord.")))?;
+        // Finding a voicing takes a while, so it is done on a blocking thread.
+        Ok(tokio::task::spawn_blocking(move || ChordDiagram::new(&chord, instrument)).await?)
     }
 
-    async fn search_spotify_tracks(&self, query: String) -> FieldResult<Vec<SpotifyTrack>> {
+    async fn search_spotify_tracks(&self, query: String) -> ChordmateResult<Vec<SpotifyTrack>> {
         let json = self.spotify_client.search_tracks(&query).await?;
 
         let tracks = json["tracks"]["items"]
@@ -62,7 +171,7 @@ impl QLQuery {
                     album_art: item
                         .get("album")
                         .and_then(|album| album.get("images"))
-                        .and_then(|images| images.as_array()?.get(0))
+                        .and_then(|images| images.as_array()?.first())
                         .and_then(|img| img.get("url"))
                         .and_then(|v| v.as_str().map(String::from)),
                 })
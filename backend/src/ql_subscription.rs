@@ -0,0 +1,64 @@
+use crate::auth::Context;
+use crate::error::ChordmateResult;
+use crate::song::Song;
+use crate::song_access::{self, SongRole};
+use crate::song_events::{SongChange, SongChangeKind, SongEvents};
+use futures::{future, Stream, StreamExt};
+use juniper::graphql_subscription;
+use std::pin::Pin;
+
+type SongStream = Pin<Box<dyn Stream<Item = ChordmateResult<Song>> + Send>>;
+type SongChangeStream = Pin<Box<dyn Stream<Item = SongChange> + Send>>;
+
+pub struct QLSubscription {
+    pub song_events: SongEvents,
+}
+
+#[graphql_subscription(context = Context)]
+impl QLSubscription {
+    /// Yields the song every time it is edited. Ends when the song is deleted, or when the
+    /// calling user may no longer see it.
+    async fn song_changed(&self, context: &Context, id: i32) -> ChordmateResult<SongStream> {
+        song_access::require_role(context, id, SongRole::Viewer).await?;
+        let songs = context.songs.clone();
+        let user_id = context.user_id();
+        let stream = self
+            .song_events
+            .subscribe()
+            .filter(move |change| future::ready(change.id == id))
+            .then(move |change| {
+                let songs = songs.clone();
+                async move {
+                    if change.kind == SongChangeKind::Deleted {
+                        return None;
+                    }
+                    match songs.role(id, user_id).await {
+                        Ok(Some(Some(_))) => songs.find(id).await.transpose(),
+                        Ok(_) => None,
+                        Err(error) => Some(Err(error)),
+                    }
+                }
+            })
+            .take_while(|song| future::ready(song.is_some()))
+            .filter_map(future::ready);
+        Ok(Box::pin(stream))
+    }
+
+    /// Yields every song the calling user may see when it is created, edited or deleted.
+    async fn songs_changed(&self, context: &Context) -> SongChangeStream {
+        let songs = context.songs.clone();
+        let user_id = context.user_id();
+        let stream = self.song_events.subscribe().filter(move |change| {
+            let songs = songs.clone();
+            let change = change.clone();
+            async move {
+                match &change.audience {
+                    Some(audience) => audience.includes(user_id),
+                    // Changes that cannot be checked are left out rather than shown to everyone.
+                    None => matches!(songs.role(change.id, user_id).await, Ok(Some(Some(_)))),
+                }
+            }
+        });
+        Box::pin(stream)
+    }
+}
@@ -0,0 +1,190 @@
+//! Setlists belong to the user who made them. Only the owner can see and change a setlist, except
+//! for setlists made before there were accounts: those can be seen by everybody and changed by
+//! nobody until they are given an owner.
+
+use crate::auth::Context;
+use crate::error::{ChordmateError, ChordmateResult};
+use crate::song::Song;
+use crate::validation;
+use juniper::GraphQLObject;
+use tokio_postgres::{Client, Error, Row, Transaction};
+
+/// The condition for the setlists `$1` may see, for queries of the `setlists` table.
+pub const VISIBLE: &str = "(setlists.owner_id IS NULL OR setlists.owner_id = $1)";
+
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = Context)]
+pub struct SetlistEntry {
+    pub id: i32,
+    pub position: i32,
+    /// The key the song is played in at this gig, if it differs from the song's usual key.
+    pub key_override: Option<String>,
+    pub notes: String,
+    pub song: Song,
+}
+
+impl SetlistEntry {
+    pub fn from_row(row: &Row) -> Result<SetlistEntry, Error> {
+        Ok(SetlistEntry {
+            id: row.try_get("entry_id")?,
+            position: row.try_get("position")?,
+            key_override: row.try_get("key_override")?,
+            notes: row.try_get("notes")?,
+            song: Song::from_row(row)?,
+        })
+    }
+}
+
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = Context)]
+pub struct Setlist {
+    pub id: i32,
+    pub name: String,
+    /// The songs in their running order.
+    pub entries: Vec<SetlistEntry>,
+}
+
+impl Setlist {
+    /// Loads the setlist in `row` together with the entries whose songs `user_id` may see.
+    pub async fn from_row(
+        client: &Client,
+        row: &Row,
+        user_id: Option<i32>,
+    ) -> Result<Setlist, Error> {
+        let id: i32 = row.try_get("id")?;
+        let statement = client
+            .prepare(
+                "SELECT e.id AS entry_id, e.position, e.key_override, e.notes, s.* \
+                 FROM setlist_entries e JOIN songs s ON s.id = e.song_id \
+                 WHERE e.setlist_id = $1 AND song_role(s.id, $2) IS NOT NULL \
+                 ORDER BY e.position",
+            )
+            .await?;
+        let entries = client
+            .query(&statement, &[&id, &user_id])
+            .await?
+            .iter()
+            .map(SetlistEntry::from_row)
+            .collect::<Result<_, _>>()?;
+        Ok(Setlist {
+            id,
+            name: row.try_get("name")?,
+            entries,
+        })
+    }
+}
+
+/// Fails unless the calling user owns the setlist.
+pub async fn require_owner(
+    client: &Client,
+    context: &Context,
+    setlist_id: i32,
+) -> ChordmateResult<()> {
+    let user = context.user()?;
+    let row = client
+        .query_opt(
+            "SELECT owner_id FROM setlists WHERE id = $1",
+            &[&setlist_id],
+        )
+        .await?
+        .ok_or_else(|| setlist_not_found(setlist_id))?;
+    check_owner(setlist_id, row.try_get("owner_id")?, user.id)
+}
+
+fn check_owner(setlist_id: i32, owner_id: Option<i32>, user_id: i32) -> ChordmateResult<()> {
+    match owner_id {
+        Some(owner_id) if owner_id == user_id => Ok(()),
+        Some(_) => Err(ChordmateError::Forbidden(format!(
+            "You need to be the owner of setlist {setlist_id}."
+        ))),
+        None => Err(ChordmateError::Forbidden(format!(
+            "Setlist {setlist_id} has no owner yet and cannot be changed."
+        ))),
+    }
+}
+
+/// Fails unless the calling user owns the setlist the entry belongs to.
+pub async fn require_entry_owner(
+    client: &Client,
+    context: &Context,
+    entry_id: i32,
+) -> ChordmateResult<()> {
+    context.user()?;
+    let row = client
+        .query_opt(
+            "SELECT setlist_id FROM setlist_entries WHERE id = $1",
+            &[&entry_id],
+        )
+        .await?
+        .ok_or_else(|| {
+            ChordmateError::NotFound(format!("There is no setlist entry with id {entry_id}."))
+        })?;
+    require_owner(client, context, row.try_get("setlist_id")?).await
+}
+
+/// Locks a setlist until the transaction ends, so that changes to its entries take turns instead
+/// of handing out the same position twice.
+pub async fn lock(transaction: &Transaction<'_>, setlist_id: i32) -> ChordmateResult<()> {
+    transaction
+        .query_opt(
+            "SELECT id FROM setlists WHERE id = $1 FOR UPDATE",
+            &[&setlist_id],
+        )
+        .await?
+        .ok_or_else(|| setlist_not_found(setlist_id))?;
+    Ok(())
+}
+
+/// Checks that a new running order lists every entry of a setlist exactly once.
+pub fn check_order(mut current: Vec<i32>, requested: &[i32]) -> ChordmateResult<()> {
+    let mut requested = requested.to_vec();
+    current.sort();
+    requested.sort();
+    if current != requested {
+        return Err(validation::invalid(
+            "entryIds",
+            "The entry ids do not match the entries of the setlist.",
+        ));
+    }
+    Ok(())
+}
+
+pub fn setlist_not_found(id: i32) -> ChordmateError {
+    ChordmateError::NotFound(format!("There is no setlist with id {id}."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_owner_may_change_a_setlist() {
+        assert_eq!(check_owner(3, Some(1), 1), Ok(()));
+        assert_eq!(
+            check_owner(3, Some(2), 1),
+            Err(ChordmateError::Forbidden(String::from(
+                "You need to be the owner of setlist 3."
+            )))
+        );
+        assert!(matches!(
+            check_owner(3, None, 1),
+            Err(ChordmateError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn a_new_order_lists_every_entry_once() {
+        assert_eq!(check_order(vec![4, 7, 9], &[9, 4, 7]), Ok(()));
+        assert_eq!(check_order(Vec::new(), &[]), Ok(()));
+        for requested in [&[4, 7][..], &[4, 7, 9, 10], &[4, 7, 7], &[4, 7, 7, 9]] {
+            assert_eq!(
+                check_order(vec![4, 7, 9], requested),
+                Err(validation::invalid(
+                    "entryIds",
+                    "The entry ids do not match the entries of the setlist."
+                )),
+                "{requested:?}"
+            );
+        }
+    }
+}
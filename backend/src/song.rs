@@ -1,13 +1,34 @@
-use juniper::GraphQLObject;
+use crate::auth::{Context, User};
+use crate::capo::{self, CapoSuggestion, MAX_CAPO};
+use crate::chord::{Chord, Key};
+use crate::chordpro;
+use crate::error::{ChordmateError, ChordmateResult};
+use crate::key_detection::{self, DetectedKey};
+use crate::song_access::{self, SongPermission, SongRole};
+use crate::song_content::{self, ParsedSong};
+use crate::song_diff::{self, DiffLine};
+use crate::song_revision::SongRevision;
+use crate::validation::{self, Validator};
+use juniper::graphql_object;
+use std::borrow::Cow;
 use tokio_postgres::{Error, Row};
 
-#[derive(GraphQLObject, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct Song {
     pub id: i32,
     pub title: String,
     pub artist: String,
     pub spotify_track: String,
-    pub content: String,
+    /// `None` for songs found with [`SongRepository::search`], which leaves the content out of
+    /// lists until a resolver asks for it.
+    pub content: Option<String>,
+    pub key: Option<String>,
+    pub bpm: Option<i32>,
+    pub time_signature: Option<String>,
+    pub capo: Option<i32>,
+    pub duration_seconds: Option<i32>,
+    /// `None` for songs written before there were accounts.
+    pub owner_id: Option<i32>,
 }
 
 impl Song {
@@ -17,7 +38,244 @@ impl Song {
             title: row.try_get("title")?,
             artist: row.try_get("artist")?,
             spotify_track: row.try_get("spotify_track")?,
-            content: row.try_get("content")?,
+            content: match row
+                .columns()
+                .iter()
+                .any(|column| column.name() == "content")
+            {
+                true => Some(row.try_get("content")?),
+                false => None,
+            },
+            key: row.try_get("key")?,
+            bpm: row.try_get("bpm")?,
+            time_signature: row.try_get("time_signature")?,
+            capo: row.try_get("capo")?,
+            duration_seconds: row.try_get("duration_seconds")?,
+            owner_id: row.try_get("owner_id")?,
         })
     }
+
+    /// The key set on the song, if it is one.
+    pub fn stored_key(&self) -> Option<Key> {
+        self.key.as_deref().and_then(Key::parse)
+    }
+
+    /// The content of a song loaded by id. Resolvers use [`Song::load_content`], as songs in lists
+    /// come without it.
+    pub fn loaded_content(&self) -> &str {
+        self.content.as_deref().unwrap_or_default()
+    }
+
+    /// The content, loaded now if the song was found with a search.
+    pub async fn load_content(&self, context: &Context) -> ChordmateResult<Cow<'_, str>> {
+        if let Some(content) = &self.content {
+            return Ok(Cow::Borrowed(content));
+        }
+        let song = context.songs.find(self.id).await?.ok_or_else(|| {
+            ChordmateError::NotFound(format!("There is no song with id {}.", self.id))
+        })?;
+        Ok(Cow::Owned(song.content.unwrap_or_default()))
+    }
+
+    pub fn to_chord_pro(&self) -> String {
+        chord_pro(self, self.loaded_content())
+    }
+}
+
+fn chord_pro(song: &Song, content: &str) -> String {
+    chordpro::render(&song.title, &song.artist, &ParsedSong::from_html(content))
+}
+
+#[graphql_object(context = Context)]
+impl Song {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn artist(&self) -> &str {
+        &self.artist
+    }
+
+    fn spotify_track(&self) -> &str {
+        &self.spotify_track
+    }
+
+    async fn content(&self, context: &Context) -> ChordmateResult<String> {
+        Ok(self.load_content(context).await?.into_owned())
+    }
+
+    /// The key the song is written in, e.g. `G` or `F#m`.
+    fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Beats per minute.
+    fn bpm(&self) -> Option<i32> {
+        self.bpm
+    }
+
+    fn time_signature(&self) -> Option<&str> {
+        self.time_signature.as_deref()
+    }
+
+    /// The fret the capo is put on.
+    fn capo(&self) -> Option<i32> {
+        self.capo
+    }
+
+    fn duration_seconds(&self) -> Option<i32> {
+        self.duration_seconds
+    }
+
+    /// The content broken down into sections, lines and the chords played on them.
+    async fn parsed_content(&self, context: &Context) -> ChordmateResult<ParsedSong> {
+        Ok(ParsedSong::from_html(&self.load_content(context).await?))
+    }
+
+    /// Every chord played in the song once, in the order they first appear.
+    async fn chords(&self, context: &Context) -> ChordmateResult<Vec<Chord>> {
+        let song = ParsedSong::from_html(&self.load_content(context).await?);
+        Ok(song.distinct_chords())
+    }
+
+    /// The key the song seems to be in, guessed from its chords, whether or not `key` is set.
+    async fn detected_key(&self, context: &Context) -> ChordmateResult<Option<DetectedKey>> {
+        let song = ParsedSong::from_html(&self.load_content(context).await?);
+        Ok(
+            key_detection::detect_key(&song).map(|(key, confidence)| DetectedKey {
+                key: key.to_string(),
+                confidence,
+            }),
+        )
+    }
+
+    /// Capo positions from no capo up to `max_fret`, ranked by how many of the chords can then be
+    /// played with open shapes on a guitar.
+    async fn capo_suggestions(
+        &self,
+        context: &Context,
+        #[graphql(default = 7)] max_fret: i32,
+    ) -> ChordmateResult<Vec<CapoSuggestion>> {
+        let mut validator = Validator::new();
+        validator.range("maxFret", max_fret, 0, MAX_CAPO);
+        validator.finish()?;
+        let song = ParsedSong::from_html(&self.load_content(context).await?);
+        let key = self
+            .stored_key()
+            .or_else(|| key_detection::detect_key(&song).map(|(key, _)| key));
+        Ok(capo::suggestions(&song, max_fret as u8, key))
+    }
+
+    /// The content with every chord moved by the given number of semitones.
+    async fn transposed_content(
+        &self,
+        context: &Context,
+        semitones: i32,
+        prefer_flats: Option<bool>,
+    ) -> ChordmateResult<String> {
+        let prefer_flats = prefer_flats.or_else(|| {
+            self.stored_key()
+                .map(|key| key.transpose(semitones).prefers_flats())
+        });
+        let content = self.load_content(context).await?;
+        Ok(song_content::transpose_html(
+            &content,
+            semitones,
+            prefer_flats,
+        ))
+    }
+
+    /// The content with every chord written as a Nashville number relative to `key`, or to the
+    /// key of the song if it is left out. Fails if neither is known.
+    async fn nashville_content(
+        &self,
+        context: &Context,
+        key: Option<String>,
+    ) -> ChordmateResult<String> {
+        let content = self.load_content(context).await?;
+        let song = ParsedSong::from_html(&content);
+        let key = match key {
+            Some(key) => Some(
+                Key::parse(&key)
+                    .ok_or_else(|| validation::invalid("key", format!("'{key}' is not a key.")))?,
+            ),
+            None => self
+                .stored_key()
+                .or_else(|| key_detection::detect_key(&song).map(|(key, _)| key)),
+        };
+        // Without a key the chords cannot be numbered.
+        let key = key.ok_or_else(|| {
+            validation::invalid("key", "The song has no key, `key` has to be given.")
+        })?;
+        Ok(song_content::nashville_html(&content, &key))
+    }
+
+    async fn export_chord_pro(&self, context: &Context) -> ChordmateResult<String> {
+        Ok(chord_pro(self, &self.load_content(context).await?))
+    }
+
+    async fn tags(&self, context: &Context) -> ChordmateResult<Vec<String>> {
+        context.songs.tags(self.id).await
+    }
+
+    /// Every saved version of the content, the most recent first.
+    async fn revisions(&self, context: &Context) -> ChordmateResult<Vec<SongRevision>> {
+        context.songs.revisions(self.id).await
+    }
+
+    /// What changed between two revisions, or between a revision and the current content if
+    /// `to_revision` is left out.
+    async fn diff(
+        &self,
+        context: &Context,
+        from_revision: i32,
+        to_revision: Option<i32>,
+    ) -> ChordmateResult<Vec<DiffLine>> {
+        let mut contents = Vec::with_capacity(2);
+        for revision_id in [Some(from_revision), to_revision] {
+            let Some(revision_id) = revision_id else {
+                contents.push(self.load_content(context).await?.into_owned());
+                continue;
+            };
+            match context.songs.revision(self.id, revision_id).await? {
+                Some(revision) => contents.push(revision.content),
+                None => {
+                    return Err(ChordmateError::NotFound(format!(
+                        "Song {} has no revision {revision_id}.",
+                        self.id
+                    )))
+                }
+            }
+        }
+        Ok(song_diff::diff(
+            &ParsedSong::from_html(&contents[0]),
+            &ParsedSong::from_html(&contents[1]),
+        ))
+    }
+
+    async fn owner(&self, context: &Context) -> ChordmateResult<Option<User>> {
+        let Some(owner_id) = self.owner_id else {
+            return Ok(None);
+        };
+        context.songs.user(owner_id).await
+    }
+
+    /// What the calling user may do with this song.
+    async fn role(&self, context: &Context) -> ChordmateResult<Option<SongRole>> {
+        Ok(context
+            .songs
+            .role(self.id, context.user_id())
+            .await?
+            .flatten())
+    }
+
+    /// The users the song is shared with. Only visible to its owner.
+    async fn permissions(&self, context: &Context) -> ChordmateResult<Vec<SongPermission>> {
+        song_access::require_role(context, self.id, SongRole::Owner).await?;
+        context.songs.permissions(self.id).await
+    }
 }
@@ -0,0 +1,85 @@
+//! Who may see and change which song.
+//!
+//! Every song has an owner who can share it with other users as a viewer or an editor. Songs
+//! written before there were accounts have no owner: anyone can read them and every logged-in
+//! user can edit them until they are given an owner with the `assign-owner` command.
+
+use crate::auth::{Context, User};
+use crate::error::{ChordmateError, ChordmateResult};
+use juniper::{GraphQLEnum, GraphQLObject};
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SongRole {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl SongRole {
+    /// The name the role is stored under in the database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SongRole::Viewer => "viewer",
+            SongRole::Editor => "editor",
+            SongRole::Owner => "owner",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<SongRole> {
+        match role {
+            "viewer" => Some(SongRole::Viewer),
+            "editor" => Some(SongRole::Editor),
+            "owner" => Some(SongRole::Owner),
+            _ => None,
+        }
+    }
+}
+
+/// Who may see a song.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SongAudience {
+    /// Songs without an owner can be read by anyone.
+    Everyone,
+    /// The owner and the users the song is shared with.
+    Users(Vec<i32>),
+}
+
+impl SongAudience {
+    pub fn includes(&self, user_id: Option<i32>) -> bool {
+        match self {
+            SongAudience::Everyone => true,
+            SongAudience::Users(users) => user_id.is_some_and(|id| users.contains(&id)),
+        }
+    }
+}
+
+#[derive(GraphQLObject, Clone, Debug)]
+pub struct SongPermission {
+    pub user: User,
+    pub role: SongRole,
+}
+
+/// Fails unless the calling user has at least the `required` role on a song.
+pub async fn require_role(
+    context: &Context,
+    song_id: i32,
+    required: SongRole,
+) -> ChordmateResult<SongRole> {
+    match context.songs.role(song_id, context.user_id()).await? {
+        None => Err(ChordmateError::NotFound(format!(
+            "There is no song with id {song_id}."
+        ))),
+        Some(Some(role)) if role >= required => Ok(role),
+        Some(_) => {
+            context.user()?;
+            Err(ChordmateError::Forbidden(format!(
+                "You need to be {} of song {song_id}.",
+                match required {
+                    SongRole::Owner => "the owner",
+                    SongRole::Editor => "an editor",
+                    SongRole::Viewer => "a viewer",
+                }
+            )))
+        }
+    }
+}
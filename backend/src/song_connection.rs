@@ -0,0 +1,187 @@
+//! A Relay-style connection for paging through songs.
+
+use crate::auth::Context;
+use crate::error::ChordmateResult;
+use crate::song::Song;
+use crate::song_search::{self, SongFilter};
+use juniper::{graphql_object, GraphQLObject};
+
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = Context)]
+pub struct SongEdge {
+    pub cursor: String,
+    pub node: Song,
+}
+
+#[derive(GraphQLObject, Clone, Debug, PartialEq)]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub has_previous_page: bool,
+    pub start_cursor: Option<String>,
+    pub end_cursor: Option<String>,
+}
+
+pub struct SongConnection {
+    filter: SongFilter,
+    edges: Vec<SongEdge>,
+    page_info: PageInfo,
+}
+
+impl SongConnection {
+    /// Loads at most `first` songs matching `filter`, starting after the song `after` points at.
+    pub async fn load(
+        context: &Context,
+        filter: SongFilter,
+        first: Option<i64>,
+        after: Option<&str>,
+    ) -> ChordmateResult<SongConnection> {
+        let offset = song_search::offset_after(after)?;
+        // One song more than asked for tells whether there is a next page.
+        let mut songs = context
+            .songs
+            .search(
+                &filter,
+                context.user_id(),
+                offset,
+                first.map(|first| first + 1),
+            )
+            .await?;
+        let has_next_page = first.is_some_and(|first| songs.len() as i64 > first);
+        if let Some(first) = first {
+            songs.truncate(first as usize);
+        }
+        let edges: Vec<SongEdge> = songs
+            .into_iter()
+            .enumerate()
+            .map(|(i, song)| SongEdge {
+                cursor: song_search::encode_cursor(offset + i as i64),
+                node: song,
+            })
+            .collect();
+        let page_info = PageInfo {
+            has_next_page,
+            has_previous_page: offset > 0,
+            start_cursor: edges.first().map(|edge| edge.cursor.clone()),
+            end_cursor: edges.last().map(|edge| edge.cursor.clone()),
+        };
+        Ok(SongConnection {
+            filter,
+            edges,
+            page_info,
+        })
+    }
+}
+
+#[graphql_object(context = Context)]
+impl SongConnection {
+    fn edges(&self) -> &[SongEdge] {
+        &self.edges
+    }
+
+    fn page_info(&self) -> &PageInfo {
+        &self.page_info
+    }
+
+    /// How many songs there are on all pages together.
+    async fn total_count(&self, context: &Context) -> ChordmateResult<i32> {
+        Ok(context.songs.count(&self.filter, context.user_id()).await? as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::User;
+    use crate::database_connection::DatabaseConnection;
+    use crate::error::ChordmateError;
+    use crate::song_repository::{InMemorySongRepository, SongRepository};
+    use std::sync::Arc;
+
+    /// The context of the owner of songs titled `Song 1` to `Song {count}`.
+    async fn context(count: usize) -> Context {
+        let songs = InMemorySongRepository::new();
+        for i in 1..=count {
+            songs.create(1, &format!("Song {i}"), "", "").await.unwrap();
+        }
+        Context {
+            user: Some(User {
+                id: 1,
+                username: String::from("user1"),
+            }),
+            ..Context::anonymous(DatabaseConnection::disconnected(), Arc::new(songs))
+        }
+    }
+
+    async fn page(context: &Context, first: Option<i64>, after: Option<&str>) -> SongConnection {
+        SongConnection::load(context, SongFilter::default(), first, after)
+            .await
+            .unwrap()
+    }
+
+    fn titles(connection: &SongConnection) -> Vec<&str> {
+        let edges = connection.edges.iter();
+        edges.map(|edge| edge.node.title.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn pages_follow_each_other() {
+        let context = context(5).await;
+        let first = page(&context, Some(2), None).await;
+        assert_eq!(titles(&first), ["Song 1", "Song 2"]);
+        assert_eq!(
+            first.page_info,
+            PageInfo {
+                has_next_page: true,
+                has_previous_page: false,
+                start_cursor: Some(String::from("song:0")),
+                end_cursor: Some(String::from("song:1")),
+            }
+        );
+
+        let second = page(&context, Some(2), first.page_info.end_cursor.as_deref()).await;
+        assert_eq!(titles(&second), ["Song 3", "Song 4"]);
+        assert!(second.page_info.has_next_page);
+        assert!(second.page_info.has_previous_page);
+
+        let last = page(&context, Some(2), second.page_info.end_cursor.as_deref()).await;
+        assert_eq!(titles(&last), ["Song 5"]);
+        assert!(!last.page_info.has_next_page);
+        assert_eq!(last.page_info.end_cursor.as_deref(), Some("song:4"));
+    }
+
+    #[tokio::test]
+    async fn a_page_that_ends_with_the_last_song_has_no_next_page() {
+        let context = context(4).await;
+        let connection = page(&context, Some(2), Some("song:1")).await;
+        assert_eq!(titles(&connection), ["Song 3", "Song 4"]);
+        assert!(!connection.page_info.has_next_page);
+
+        let connection = page(&context, Some(4), None).await;
+        assert!(!connection.page_info.has_next_page);
+    }
+
+    #[tokio::test]
+    async fn pages_can_be_empty_or_unlimited() {
+        let context = context(3).await;
+        let connection = page(&context, None, None).await;
+        assert_eq!(titles(&connection), ["Song 1", "Song 2", "Song 3"]);
+        assert!(!connection.page_info.has_next_page);
+
+        let connection = page(&context, Some(0), None).await;
+        assert!(connection.edges.is_empty());
+        assert!(connection.page_info.has_next_page);
+        assert_eq!(connection.page_info.start_cursor, None);
+
+        let connection = page(&context, Some(2), Some("song:7")).await;
+        assert!(connection.edges.is_empty());
+        assert!(!connection.page_info.has_next_page);
+        assert!(connection.page_info.has_previous_page);
+    }
+
+    #[tokio::test]
+    async fn invalid_cursors_are_rejected() {
+        let context = context(1).await;
+        let result = SongConnection::load(&context, SongFilter::default(), None, Some("x")).await;
+        assert!(matches!(result, Err(ChordmateError::Validation(_))));
+    }
+}
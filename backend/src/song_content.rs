@@ -0,0 +1,748 @@
+//! The structured representation of a song's content.
+//!
+//! The editor stores songs as HTML where chords are written on their own line above the lyrics
+//! they belong to. This module turns that into sections, lines and chords attached to character
+//! offsets in the lyrics, and back.
+
+use crate::chord::{Chord, Key};
+use crate::html::{self, BlockKind};
+use crate::key_detection;
+use juniper::{graphql_object, GraphQLObject};
+use std::ops::Range;
+
+#[derive(GraphQLObject, Clone, Debug, PartialEq)]
+pub struct ChordAnnotation {
+    /// Character offset into the lyrics of the line where this chord is played.
+    pub offset: i32,
+    pub symbol: String,
+}
+
+impl ChordAnnotation {
+    /// The chord this annotation stands for, if it is not just a bar line or a repeat sign.
+    pub fn chord(&self) -> Option<Chord> {
+        Chord::parse(self.symbol.trim_start_matches('(').trim_end_matches(')'))
+    }
+
+    /// The symbol with its chord rewritten, keeping the parentheses around optional chords.
+    /// `None` for bar lines and repeat signs.
+    fn map_chord(&self, rewrite: impl Fn(&Chord) -> String) -> Option<String> {
+        let chord = self.chord()?;
+        let open = self.symbol.len() - self.symbol.trim_start_matches('(').len();
+        let close = self.symbol.len() - self.symbol.trim_end_matches(')').len();
+        Some(format!(
+            "{}{}{}",
+            "(".repeat(open),
+            rewrite(&chord),
+            ")".repeat(close)
+        ))
+    }
+}
+
+#[derive(GraphQLObject, Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub chord: Option<String>,
+    pub lyrics: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Line {
+    pub chords: Vec<ChordAnnotation>,
+    pub lyrics: String,
+}
+
+#[graphql_object]
+impl Line {
+    fn chords(&self) -> &[ChordAnnotation] {
+        &self.chords
+    }
+
+    fn lyrics(&self) -> &str {
+        &self.lyrics
+    }
+
+    /// The lyrics split at every chord, each piece together with the chord it starts with.
+    fn segments(&self) -> Vec<Segment> {
+        let lyrics: Vec<char> = self.lyrics.chars().collect();
+        let slice = |from: i32, to: i32| -> String {
+            let from = (from.max(0) as usize).min(lyrics.len());
+            let to = (to.max(0) as usize).clamp(from, lyrics.len());
+            lyrics[from..to].iter().collect()
+        };
+        let mut segments = Vec::new();
+        let first_offset = self
+            .chords
+            .first()
+            .map_or(lyrics.len() as i32, |c| c.offset);
+        if first_offset > 0 {
+            segments.push(Segment {
+                chord: None,
+                lyrics: slice(0, first_offset),
+            });
+        }
+        for (i, chord) in self.chords.iter().enumerate() {
+            let end = self
+                .chords
+                .get(i + 1)
+                .map_or(lyrics.len() as i32, |next| next.offset);
+            segments.push(Segment {
+                chord: Some(chord.symbol.clone()),
+                lyrics: slice(chord.offset, end),
+            });
+        }
+        segments
+    }
+}
+
+impl Line {
+    /// Renders the chords of this line as a line of text to be placed above the lyrics.
+    pub fn chord_line(&self) -> String {
+        let mut line = String::new();
+        let mut width = 0;
+        for chord in &self.chords {
+            let offset = chord.offset.max(0) as usize;
+            let padding = if offset > width {
+                offset - width
+            } else if width > 0 {
+                1
+            } else {
+                0
+            };
+            line.push_str(&" ".repeat(padding));
+            line.push_str(&chord.symbol);
+            width += padding + chord.symbol.chars().count();
+        }
+        line
+    }
+}
+
+#[derive(GraphQLObject, Clone, Debug, Default, PartialEq)]
+pub struct Section {
+    /// The section's name, e.g. "Chorus" or "Verse 2".
+    pub label: Option<String>,
+    pub lines: Vec<Line>,
+}
+
+#[derive(GraphQLObject, Clone, Debug, Default, PartialEq)]
+pub struct ParsedSong {
+    pub sections: Vec<Section>,
+}
+
+const SECTION_NAMES: [&str; 14] = [
+    "intro",
+    "verse",
+    "pre-chorus",
+    "prechorus",
+    "chorus",
+    "refrain",
+    "bridge",
+    "interlude",
+    "instrumental",
+    "solo",
+    "break",
+    "tag",
+    "coda",
+    "outro",
+];
+
+/// Recognizes lines like `[Chorus]`, `Verse 2:` or `Outro` that name a section.
+pub fn section_label(text: &str) -> Option<String> {
+    let text = text.trim();
+    let label = text
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .or_else(|| text.strip_suffix(':'))
+        .unwrap_or(text)
+        .trim();
+    let name = label.split_whitespace().next()?.to_lowercase();
+    let is_section = SECTION_NAMES.contains(&name.as_str())
+        && label.split_whitespace().count() <= 3
+        && label.split_whitespace().skip(1).all(|word| {
+            word.chars()
+                .all(|c| c.is_ascii_digit() || "x()".contains(c))
+        });
+    is_section.then(|| label.to_string())
+}
+
+/// Symbols that may appear on a chord line without being chords themselves.
+fn is_chord_line_decoration(token: &str) -> bool {
+    matches!(
+        token,
+        "|" | "||" | "/" | "-" | "%" | "N.C." | "NC" | "(" | ")"
+    ) || token
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .strip_prefix(['x', 'X'])
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// The tokens of a line that is meant to be a line of chords but does not parse as one, because
+/// some symbols are not chords, e.g. `Hm7` in `G  C  Hm7  D`.
+///
+/// A line counts as meant to be chords if at least two chords and more chords than other tokens
+/// are written on it, and every other token starts like a chord.
+pub fn unknown_chord_symbols(text: &str) -> Vec<&str> {
+    if parse_chord_line(text).is_some() {
+        return Vec::new();
+    }
+    let mut chords = 0;
+    let mut unknown = Vec::new();
+    for token in text.split_whitespace() {
+        if Chord::parse(token.trim_start_matches('(').trim_end_matches(')')).is_some() {
+            chords += 1;
+        } else if !is_chord_line_decoration(token) {
+            unknown.push(token);
+        }
+    }
+    let looks_like_chord = |token: &&str| token.starts_with(|c: char| ('A'..='H').contains(&c));
+    if chords >= 2 && chords > unknown.len() && unknown.iter().all(looks_like_chord) {
+        unknown
+    } else {
+        Vec::new()
+    }
+}
+
+/// Returns the chords of `text` if it is a line that consists of chords only.
+pub fn parse_chord_line(text: &str) -> Option<Vec<ChordAnnotation>> {
+    let mut chords = Vec::new();
+    let mut has_chord = false;
+    let mut offset = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let leading = rest.len() - rest.trim_start().len();
+        offset += rest[..leading].chars().count();
+        rest = &rest[leading..];
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if end == 0 {
+            break;
+        }
+        let token = &rest[..end];
+        let annotation = ChordAnnotation {
+            offset: offset as i32,
+            symbol: token.to_string(),
+        };
+        if annotation.chord().is_some() {
+            has_chord = true;
+        } else if !is_chord_line_decoration(token) {
+            return None;
+        }
+        chords.push(annotation);
+        offset += token.chars().count();
+        rest = &rest[end..];
+    }
+    has_chord.then_some(chords)
+}
+
+/// Replaces tabs by spaces up to the next multiple of eight columns, so that chords written with
+/// tabs stay aligned with their lyrics.
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let width = 8 - column % 8;
+            expanded.push_str(&" ".repeat(width));
+            column += width;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    expanded
+}
+
+/// Assembles a [`ParsedSong`] line by line, shared by the parsers of the various song formats.
+pub(crate) struct SongBuilder {
+    song: ParsedSong,
+    section: Section,
+    pending_chords: Option<Vec<ChordAnnotation>>,
+}
+
+impl SongBuilder {
+    pub(crate) fn new() -> Self {
+        SongBuilder {
+            song: ParsedSong::default(),
+            section: Section::default(),
+            pending_chords: None,
+        }
+    }
+
+    fn flush_chords(&mut self) {
+        if let Some(chords) = self.pending_chords.take() {
+            self.section.lines.push(Line {
+                chords,
+                lyrics: String::new(),
+            });
+        }
+    }
+
+    pub(crate) fn start_section(&mut self, label: Option<String>) {
+        self.flush_chords();
+        let section = std::mem::replace(
+            &mut self.section,
+            Section {
+                label,
+                lines: vec![],
+            },
+        );
+        if !section.lines.is_empty() || section.label.is_some() {
+            self.song.sections.push(section);
+        }
+    }
+
+    pub(crate) fn blank_line(&mut self) {
+        self.flush_chords();
+        if !self.section.lines.is_empty() {
+            self.start_section(None);
+        }
+    }
+
+    fn chord_line(&mut self, chords: Vec<ChordAnnotation>) {
+        self.flush_chords();
+        self.pending_chords = Some(chords);
+    }
+
+    fn lyrics_line(&mut self, lyrics: &str) {
+        self.section.lines.push(Line {
+            chords: self.pending_chords.take().unwrap_or_default(),
+            lyrics: lyrics.trim_end().to_string(),
+        });
+    }
+
+    pub(crate) fn line(&mut self, line: Line) {
+        self.flush_chords();
+        self.section.lines.push(line);
+    }
+
+    pub(crate) fn text_line(&mut self, text: &str) {
+        if text.trim().is_empty() {
+            self.blank_line();
+        } else if let Some(label) = section_label(text) {
+            self.start_section(Some(label));
+        } else if let Some(chords) = parse_chord_line(text) {
+            self.chord_line(chords);
+        } else {
+            self.lyrics_line(text);
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> ParsedSong {
+        self.start_section(None);
+        self.song
+    }
+}
+
+impl ParsedSong {
+    pub fn from_html(content: &str) -> ParsedSong {
+        let mut builder = SongBuilder::new();
+        for line in html::text_lines(content) {
+            match line.kind {
+                BlockKind::Heading(_) if !line.text.trim().is_empty() => {
+                    builder.start_section(Some(line.text.trim().to_string()));
+                }
+                _ => builder.text_line(&line.text),
+            }
+        }
+        builder.finish()
+    }
+
+    /// Moves every chord of the song up (or down, for negative values) by `semitones`.
+    ///
+    /// Accidentals are written as flats or sharps as requested, or as is conventional for the key
+    /// the song ends up in, judged by the key detected from its chords.
+    pub fn transpose(&self, semitones: i32, prefer_flats: Option<bool>) -> ParsedSong {
+        let prefer_flats = prefer_flats.unwrap_or_else(|| self.prefers_flats_after(semitones));
+        self.map_chords(|chord| chord.transpose(semitones, prefer_flats).to_string())
+    }
+
+    /// Whether the key the song is in after moving it by `semitones` is written with flats.
+    fn prefers_flats_after(&self, semitones: i32) -> bool {
+        key_detection::detect_key(self)
+            .is_some_and(|(key, _)| key.transpose(semitones).prefers_flats())
+    }
+
+    /// The song with its chords written as Nashville numbers relative to `key`, e.g. `1`, `4`,
+    /// `6m7` or `4/5`.
+    pub fn to_nashville(&self, key: &Key) -> ParsedSong {
+        self.map_chords(|chord| chord.nashville(key))
+    }
+
+    /// Rewrites every chord of the song, keeping the parentheses around optional chords.
+    fn map_chords(&self, rewrite: impl Fn(&Chord) -> String) -> ParsedSong {
+        let mut song = self.clone();
+        for line in song.sections.iter_mut().flat_map(|s| s.lines.iter_mut()) {
+            for annotation in &mut line.chords {
+                if let Some(symbol) = annotation.map_chord(&rewrite) {
+                    annotation.symbol = symbol;
+                }
+            }
+        }
+        song
+    }
+
+    pub fn chords(&self) -> impl Iterator<Item = &ChordAnnotation> {
+        self.sections
+            .iter()
+            .flat_map(|section| &section.lines)
+            .flat_map(|line| &line.chords)
+    }
+
+    /// The words of the song without chords or section labels, one line of lyrics per line.
+    pub fn lyrics(&self) -> String {
+        let lines: Vec<&str> = self
+            .sections
+            .iter()
+            .flat_map(|section| &section.lines)
+            .map(|line| line.lyrics.trim())
+            .filter(|lyrics| !lyrics.is_empty())
+            .collect();
+        lines.join("\n")
+    }
+
+    /// Every chord played in the song once, in the order they first appear.
+    pub fn distinct_chords(&self) -> Vec<Chord> {
+        let mut chords: Vec<Chord> = Vec::new();
+        for chord in self.chords().filter_map(ChordAnnotation::chord) {
+            if !chords.contains(&chord) {
+                chords.push(chord);
+            }
+        }
+        chords
+    }
+
+    /// Parses a plain-text song sheet with each line of chords written above its lyrics.
+    pub fn from_text(text: &str) -> ParsedSong {
+        let mut builder = SongBuilder::new();
+        for line in text.lines() {
+            builder.text_line(&expand_tabs(line));
+        }
+        builder.finish()
+    }
+
+    /// Renders the song in the format the editor produces, with every line of chords and every
+    /// line of lyrics in its own paragraph.
+    pub fn to_html(&self) -> String {
+        let mut content = String::new();
+        for (i, section) in self.sections.iter().enumerate() {
+            if i > 0 {
+                content.push_str("<p></p>");
+            }
+            if let Some(label) = &section.label {
+                content.push_str(&format!("<h2>{}</h2>", html::escape(label)));
+            }
+            for line in &section.lines {
+                if !line.chords.is_empty() {
+                    content.push_str(&format!(
+                        "<p>{}</p>",
+                        html::escape_preserving_spaces(&line.chord_line())
+                    ));
+                }
+                if !line.lyrics.is_empty() || line.chords.is_empty() {
+                    content.push_str(&format!(
+                        "<p>{}</p>",
+                        html::escape_preserving_spaces(&line.lyrics)
+                    ));
+                }
+            }
+        }
+        content
+    }
+}
+
+/// Moves every chord of HTML content like [`ParsedSong::transpose`], without changing anything
+/// but the chord symbols.
+pub fn transpose_html(content: &str, semitones: i32, prefer_flats: Option<bool>) -> String {
+    let prefer_flats = prefer_flats
+        .unwrap_or_else(|| ParsedSong::from_html(content).prefers_flats_after(semitones));
+    map_chords_in_html(content, |chord| {
+        chord.transpose(semitones, prefer_flats).to_string()
+    })
+}
+
+/// Writes the chords of HTML content as Nashville numbers like [`ParsedSong::to_nashville`],
+/// without changing anything but the chord symbols.
+pub fn nashville_html(content: &str, key: &Key) -> String {
+    map_chords_in_html(content, |chord| chord.nashville(key))
+}
+
+/// Rewrites the chords on the lines of chords of an HTML document where they are written.
+///
+/// Headings, lyrics, inline markup and empty paragraphs are kept byte for byte. Spaces after a
+/// chord that gets longer or shorter are taken away or added, so that the chords after it stay
+/// above the same lyrics where there is room for it.
+fn map_chords_in_html(content: &str, rewrite: impl Fn(&Chord) -> String) -> String {
+    let mut replacements: Vec<(Range<usize>, String)> = Vec::new();
+    for line in html::source_lines(content) {
+        if matches!(line.kind, BlockKind::Heading(_)) {
+            continue;
+        }
+        // Every character of the line with the piece of text it is in, and where it is written.
+        let characters: Vec<(char, usize, Range<usize>)> = line
+            .ranges
+            .iter()
+            .enumerate()
+            .flat_map(|(piece, range)| {
+                html::decode_with_ranges(&content[range.clone()])
+                    .into_iter()
+                    .map(move |(c, decoded)| {
+                        (
+                            c,
+                            piece,
+                            range.start + decoded.start..range.start + decoded.end,
+                        )
+                    })
+            })
+            .collect();
+        let text: String = characters
+            .iter()
+            .map(|&(c, ..)| if c == '\n' { ' ' } else { c })
+            .collect();
+        if section_label(&text).is_some() {
+            continue;
+        }
+        let Some(annotations) = parse_chord_line(&text) else {
+            continue;
+        };
+        for (i, annotation) in annotations.iter().enumerate() {
+            let Some(symbol) = annotation.map_chord(&rewrite) else {
+                continue;
+            };
+            let start = annotation.offset as usize;
+            let end = start + annotation.symbol.chars().count();
+            let piece = characters[start].1;
+            // A chord split up by inline markup is left alone.
+            if characters[end - 1].1 != piece {
+                continue;
+            }
+            let mut written = characters[start].2.start..characters[end - 1].2.end;
+            let mut replacement = html::escape(&symbol);
+            let old_width = end - start;
+            let new_width = symbol.chars().count();
+            if i + 1 < annotations.len() {
+                let spaces = characters[end..]
+                    .iter()
+                    .take_while(|&&(c, p, _)| c.is_whitespace() && p == piece)
+                    .count();
+                if new_width > old_width {
+                    // At least one space is kept between two chords.
+                    let removed = (new_width - old_width).min(spaces.saturating_sub(1));
+                    if removed > 0 {
+                        written.end = characters[end + removed - 1].2.end;
+                    }
+                } else {
+                    replacement.push_str(&"&nbsp;".repeat(old_width - new_width));
+                }
+            }
+            replacements.push((written, replacement));
+        }
+    }
+
+    let mut rewritten = String::with_capacity(content.len());
+    let mut position = 0;
+    for (range, replacement) in replacements {
+        rewritten.push_str(&content[position..range.start]);
+        rewritten.push_str(&replacement);
+        position = range.end;
+    }
+    rewritten.push_str(&content[position..]);
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(offset: i32, symbol: &str) -> ChordAnnotation {
+        ChordAnnotation {
+            offset,
+            symbol: symbol.to_string(),
+        }
+    }
+
+    #[test]
+    fn chords_are_placed_over_the_lyrics_below_them() {
+        let song = ParsedSong::from_html(
+            "<h2>Verse 1</h2><p>G&nbsp;&nbsp;&nbsp;&nbsp; (D)</p><p>Hello darkness</p>\
+             <p>Chorus:</p><p>Em</p><p>My old friend</p>",
+        );
+        assert_eq!(
+            song.sections,
+            [
+                Section {
+                    label: Some(String::from("Verse 1")),
+                    lines: vec![Line {
+                        chords: vec![chord(0, "G"), chord(6, "(D)")],
+                        lyrics: String::from("Hello darkness"),
+                    }],
+                },
+                Section {
+                    label: Some(String::from("Chorus")),
+                    lines: vec![Line {
+                        chords: vec![chord(0, "Em")],
+                        lyrics: String::from("My old friend"),
+                    }],
+                },
+            ]
+        );
+        assert_eq!(
+            song.sections[0].lines[0].segments(),
+            [
+                Segment {
+                    chord: Some(String::from("G")),
+                    lyrics: String::from("Hello "),
+                },
+                Segment {
+                    chord: Some(String::from("(D)")),
+                    lyrics: String::from("darkness"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_that_are_not_only_chords_are_lyrics() {
+        let song = ParsedSong::from_html("<p>A song in A</p><p>Am I wrong</p>");
+        assert!(song.chords().next().is_none());
+        assert_eq!(song.lyrics(), "A song in A\nAm I wrong");
+    }
+
+    #[test]
+    fn rendered_songs_parse_back_the_same() {
+        let content = "<h2>Intro</h2><p>C&nbsp;&nbsp;&nbsp;G</p><p></p>\
+            <h2>Verse</h2><p>Am&nbsp;&nbsp;&nbsp;&nbsp;&nbsp;F</p><p>Words &amp; more words</p>";
+        let song = ParsedSong::from_html(content);
+        assert_eq!(song.to_html(), content);
+        assert_eq!(ParsedSong::from_html(&song.to_html()), song);
+    }
+
+    #[test]
+    fn transposing_keeps_headings_emphasis_and_lists() {
+        let content = "<h1>Title</h1><h2>Verse</h2>\
+            <p>G&nbsp;&nbsp;&nbsp; D</p><p>Some <strong>bold</strong> and <em>soft</em> words</p>\
+            <p></p><ul><li>Play it twice</li></ul><p><br></p>";
+        assert_eq!(
+            transpose_html(content, 2, Some(false)),
+            "<h1>Title</h1><h2>Verse</h2>\
+            <p>A&nbsp;&nbsp;&nbsp; E</p><p>Some <strong>bold</strong> and <em>soft</em> words</p>\
+            <p></p><ul><li>Play it twice</li></ul><p><br></p>"
+        );
+    }
+
+    #[test]
+    fn transposing_keeps_chords_above_their_lyrics() {
+        assert_eq!(
+            transpose_html("<p>C&nbsp;&nbsp; F&nbsp; G</p>", 1, Some(false)),
+            "<p>C#&nbsp; F# G#</p>"
+        );
+        assert_eq!(
+            transpose_html("<p>C#&nbsp; F# G#</p>", -1, Some(false)),
+            "<p>C&nbsp;&nbsp; F&nbsp; G</p>"
+        );
+    }
+
+    #[test]
+    fn transposing_spells_chords_for_the_key_the_song_ends_up_in() {
+        assert_eq!(
+            transpose_html("<p>E&nbsp; A&nbsp; B</p>", 1, None),
+            "<p>F&nbsp; Bb C</p>"
+        );
+        assert_eq!(
+            transpose_html("<p>G&nbsp; C&nbsp; D</p>", 4, None),
+            "<p>B&nbsp; E&nbsp; F#</p>"
+        );
+    }
+
+    #[test]
+    fn transposing_leaves_lyrics_and_decorations_alone() {
+        assert_eq!(
+            transpose_html("<p>| (Am) % |</p><p>A song in A</p>", 3, Some(false)),
+            "<p>| (Cm) % |</p><p>A song in A</p>"
+        );
+    }
+
+    #[test]
+    fn nashville_numbers_replace_chords_in_place() {
+        let key = Key::parse("G").unwrap();
+        assert_eq!(
+            nashville_html("<h2>Chorus</h2><p>G&nbsp; Em C/D</p>", &key),
+            "<h2>Chorus</h2><p>1&nbsp; 6m 4/5</p>"
+        );
+    }
+
+    #[test]
+    fn text_chords_are_paired_with_the_lyrics_below_them() {
+        let song = ParsedSong::from_text("Verse:\nG\tC\nHello darkness\nD\nmy old friend\n");
+        assert_eq!(
+            song.sections,
+            [Section {
+                label: Some(String::from("Verse")),
+                lines: vec![
+                    Line {
+                        chords: vec![chord(0, "G"), chord(8, "C")],
+                        lyrics: String::from("Hello darkness"),
+                    },
+                    Line {
+                        chords: vec![chord(0, "D")],
+                        lyrics: String::from("my old friend"),
+                    },
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn text_chords_without_lyrics_get_a_line_of_their_own() {
+        let song = ParsedSong::from_text("Intro:\nC  G\nAm F\nHello");
+        assert_eq!(
+            song.sections[0].lines,
+            [
+                Line {
+                    chords: vec![chord(0, "C"), chord(3, "G")],
+                    lyrics: String::new(),
+                },
+                Line {
+                    chords: vec![chord(0, "Am"), chord(3, "F")],
+                    lyrics: String::from("Hello"),
+                },
+            ]
+        );
+        let song = ParsedSong::from_text("Hello\nC G");
+        assert_eq!(
+            song.sections[0].lines[1],
+            Line {
+                chords: vec![chord(0, "C"), chord(2, "G")],
+                lyrics: String::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn blank_text_lines_separate_sections() {
+        let song = ParsedSong::from_text("\n\nC\n\nFirst\n  \n\nSecond\n\n");
+        assert_eq!(
+            song.sections,
+            [
+                Section {
+                    label: None,
+                    lines: vec![Line {
+                        chords: vec![chord(0, "C")],
+                        lyrics: String::new(),
+                    }],
+                },
+                Section {
+                    label: None,
+                    lines: vec![Line {
+                        chords: vec![],
+                        lyrics: String::from("First"),
+                    }],
+                },
+                Section {
+                    label: None,
+                    lines: vec![Line {
+                        chords: vec![],
+                        lyrics: String::from("Second"),
+                    }],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Differences between two versions of a song.
+//!
+//! Lines are matched by their lyrics, so moving or changing a chord shows up as a change of the
+//! chords of that line rather than as a line removed and another one added.
+
+use crate::song_content::{ChordAnnotation, Line, ParsedSong};
+use juniper::{GraphQLEnum, GraphQLObject};
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffKind {
+    Unchanged,
+    Added,
+    Removed,
+    ChordsChanged,
+}
+
+/// A chord that was added (no `old`), removed (no `new`), moved to another offset or replaced.
+#[derive(GraphQLObject, Clone, Debug, PartialEq)]
+pub struct ChordChange {
+    pub old: Option<ChordAnnotation>,
+    pub new: Option<ChordAnnotation>,
+}
+
+/// Either the heading of a section, with `sectionLabel` set, or a line of the song.
+#[derive(GraphQLObject, Clone, Debug, PartialEq)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub section_label: Option<String>,
+    pub old: Option<Line>,
+    pub new: Option<Line>,
+    /// What happened to the chords of a line of kind `CHORDS_CHANGED`.
+    pub chord_changes: Vec<ChordChange>,
+}
+
+enum Item<'a> {
+    Heading(&'a str),
+    Line(&'a Line),
+}
+
+impl<'a> Item<'a> {
+    /// Items with the same key are the same line, possibly with other chords.
+    fn key(&self) -> (bool, &'a str) {
+        match self {
+            Item::Heading(label) => (true, label),
+            Item::Line(line) => (false, &line.lyrics),
+        }
+    }
+}
+
+fn items(song: &ParsedSong) -> Vec<Item<'_>> {
+    let mut items = Vec::new();
+    for section in &song.sections {
+        if let Some(label) = &section.label {
+            items.push(Item::Heading(label));
+        }
+        items.extend(section.lines.iter().map(Item::Line));
+    }
+    items
+}
+
+/// The pairs of indices of a longest common subsequence of `a` and `b`.
+fn longest_common_subsequence<T, K: PartialEq>(
+    a: &[T],
+    b: &[T],
+    key: impl Fn(&T) -> K,
+) -> Vec<(usize, usize)> {
+    // lengths[i][j] is the length of the longest common subsequence of a[i..] and b[j..].
+    let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if key(&a[i]) == key(&b[j]) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut pairs = Vec::new();
+    while i < a.len() && j < b.len() {
+        if key(&a[i]) == key(&b[j]) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+enum Step<'s, T> {
+    /// What lies between two matches: the elements only in `a` and those only in `b`.
+    Gap(&'s [T], &'s [T]),
+    Match(&'s T, &'s T),
+}
+
+/// Walks through two sequences along their longest common subsequence.
+fn align<'s, T, K: PartialEq>(a: &'s [T], b: &'s [T], key: impl Fn(&T) -> K) -> Vec<Step<'s, T>> {
+    let (mut i, mut j) = (0, 0);
+    let mut steps = Vec::new();
+    for (next_i, next_j) in longest_common_subsequence(a, b, key) {
+        steps.push(Step::Gap(&a[i..next_i], &b[j..next_j]));
+        steps.push(Step::Match(&a[next_i], &b[next_j]));
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+    steps.push(Step::Gap(&a[i..], &b[j..]));
+    steps
+}
+
+fn chord_changes(old: &[ChordAnnotation], new: &[ChordAnnotation]) -> Vec<ChordChange> {
+    let mut changes = Vec::new();
+    for step in align(old, new, |chord| chord.symbol.clone()) {
+        match step {
+            Step::Gap(removed, added) => {
+                // Chords that took the place of others are reported as replacements.
+                for i in 0..removed.len().max(added.len()) {
+                    changes.push(ChordChange {
+                        old: removed.get(i).cloned(),
+                        new: added.get(i).cloned(),
+                    });
+                }
+            }
+            Step::Match(old, new) if old.offset != new.offset => changes.push(ChordChange {
+                old: Some(old.clone()),
+                new: Some(new.clone()),
+            }),
+            Step::Match(..) => {}
+        }
+    }
+    changes
+}
+
+fn one_sided(kind: DiffKind, item: &Item) -> DiffLine {
+    let (section_label, line) = match item {
+        Item::Heading(label) => (Some(label.to_string()), None),
+        Item::Line(line) => (None, Some((*line).clone())),
+    };
+    let (old, new) = match kind {
+        DiffKind::Removed => (line, None),
+        _ => (None, line),
+    };
+    DiffLine {
+        kind,
+        section_label,
+        old,
+        new,
+        chord_changes: vec![],
+    }
+}
+
+/// Compares two versions of a song line by line.
+pub fn diff(old: &ParsedSong, new: &ParsedSong) -> Vec<DiffLine> {
+    let (old, new) = (items(old), items(new));
+    let mut lines = Vec::new();
+    for step in align(&old, &new, Item::key) {
+        match step {
+            Step::Gap(removed, added) => {
+                lines.extend(
+                    removed
+                        .iter()
+                        .map(|item| one_sided(DiffKind::Removed, item)),
+                );
+                lines.extend(added.iter().map(|item| one_sided(DiffKind::Added, item)));
+            }
+            Step::Match(Item::Line(old), Item::Line(new)) => {
+                let changes = chord_changes(&old.chords, &new.chords);
+                lines.push(DiffLine {
+                    kind: if changes.is_empty() {
+                        DiffKind::Unchanged
+                    } else {
+                        DiffKind::ChordsChanged
+                    },
+                    section_label: None,
+                    old: Some((*old).clone()),
+                    new: Some((*new).clone()),
+                    chord_changes: changes,
+                });
+            }
+            Step::Match(_, new) => lines.push(one_sided(DiffKind::Unchanged, new)),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(old: &str, new: &str) -> Vec<DiffKind> {
+        diff(&ParsedSong::from_text(old), &ParsedSong::from_text(new))
+            .iter()
+            .map(|line| line.kind)
+            .collect()
+    }
+
+    #[test]
+    fn lines_are_matched_by_their_lyrics() {
+        assert_eq!(
+            kinds(
+                "[Verse]\nG\nFirst line\nSecond line",
+                "[Verse]\nG\nFirst line\nA new line\nSecond line"
+            ),
+            [
+                DiffKind::Unchanged,
+                DiffKind::Unchanged,
+                DiffKind::Added,
+                DiffKind::Unchanged
+            ]
+        );
+        assert_eq!(
+            kinds(
+                "[Verse]\nFirst line\n[Chorus]\nRefrain",
+                "[Verse]\nFirst line"
+            ),
+            [
+                DiffKind::Unchanged,
+                DiffKind::Unchanged,
+                DiffKind::Removed,
+                DiffKind::Removed
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_chords_are_reported_per_chord() {
+        let old = ParsedSong::from_text("G     C     D\nThe same words here");
+        let new = ParsedSong::from_text("G  C        Em\nThe same words here");
+        let lines = diff(&old, &new);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].kind, DiffKind::ChordsChanged);
+        let annotation = |offset, symbol: &str| {
+            Some(ChordAnnotation {
+                offset,
+                symbol: symbol.to_string(),
+            })
+        };
+        assert_eq!(
+            lines[0].chord_changes,
+            [
+                ChordChange {
+                    old: annotation(6, "C"),
+                    new: annotation(3, "C"),
+                },
+                ChordChange {
+                    old: annotation(12, "D"),
+                    new: annotation(12, "Em"),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use crate::song_access::SongAudience;
+use futures::Stream;
+use juniper::{GraphQLEnum, GraphQLObject};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// How many changes a slow subscriber may fall behind before it starts missing some.
+const CAPACITY: usize = 64;
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SongChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+#[derive(GraphQLObject, Clone, Debug)]
+pub struct SongChange {
+    pub id: i32,
+    pub kind: SongChangeKind,
+    /// Who could see a deleted song, as that cannot be looked up anymore once it is gone.
+    #[graphql(skip)]
+    pub audience: Option<SongAudience>,
+}
+
+/// Notifies subscribers about songs being created, edited or deleted.
+///
+/// Clones share the same channel.
+#[derive(Clone)]
+pub struct SongEvents {
+    sender: broadcast::Sender<SongChange>,
+}
+
+impl Default for SongEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SongEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        SongEvents { sender }
+    }
+
+    pub fn publish(&self, id: i32, kind: SongChangeKind) {
+        self.send(SongChange {
+            id,
+            kind,
+            audience: None,
+        });
+    }
+
+    /// Tells the users in `audience` that a song they could see was deleted.
+    pub fn publish_deleted(&self, id: i32, audience: SongAudience) {
+        self.send(SongChange {
+            id,
+            kind: SongChangeKind::Deleted,
+            audience: Some(audience),
+        });
+    }
+
+    fn send(&self, change: SongChange) {
+        // Sending only fails if nobody is listening, which is fine.
+        let _ = self.sender.send(change);
+    }
+
+    pub fn subscribe(&self) -> impl Stream<Item = SongChange> + Send + 'static {
+        futures::stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(change) => return Some((change, receiver)),
+                    // Missed changes are not worth ending the subscription for.
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
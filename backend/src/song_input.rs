@@ -0,0 +1,224 @@
+//! Changes to the details of a song, as sent with the `updateSong` mutation.
+
+use crate::capo::MAX_CAPO;
+use crate::chord::Key;
+use crate::error::ChordmateResult;
+use crate::song_repository::SongUpdate;
+use crate::validation::{in_range, Validator, MAX_NAME_LENGTH};
+use juniper::{GraphQLInputObject, Nullable};
+
+/// The fields to change. Fields that are left out keep their value, fields that are set to
+/// `null` are cleared.
+#[derive(GraphQLInputObject, Clone, Debug, Default)]
+pub struct SongInput {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub spotify_track: Option<String>,
+    /// The key the song is written in, e.g. `G` or `F#m`.
+    pub key: Nullable<String>,
+    /// Beats per minute.
+    pub bpm: Nullable<i32>,
+    /// E.g. `4/4` or `6/8`.
+    pub time_signature: Nullable<String>,
+    /// The fret the capo is put on.
+    pub capo: Nullable<i32>,
+    pub duration_seconds: Nullable<i32>,
+}
+
+const MAX_BPM: i32 = 400;
+const MAX_DURATION_SECONDS: i32 = 24 * 60 * 60;
+
+/// Parses time signatures like `4/4`, `3/4`, `6/8` or `7/8`.
+fn normalize_time_signature(time_signature: &str) -> Option<String> {
+    let (beats, value) = time_signature.trim().split_once('/')?;
+    let beats: u8 = beats.trim().parse().ok()?;
+    let value: u8 = value.trim().parse().ok()?;
+    ((1..=32).contains(&beats) && [1, 2, 4, 8, 16, 32].contains(&value))
+        .then(|| format!("{beats}/{value}"))
+}
+
+/// Spotify track ids are 22 characters of base 62, an empty id removes the track.
+fn is_spotify_track_id(id: &str) -> bool {
+    id.is_empty() || (id.len() == 22 && id.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Validates a nullable field, `Some(None)` if it is to be cleared and `None` if it was left out
+/// or is invalid.
+fn nullable<T, U>(
+    validator: &mut Validator,
+    field: &str,
+    value: Nullable<T>,
+    check: impl FnOnce(T) -> Result<U, String>,
+) -> Option<Option<U>> {
+    match value {
+        Nullable::ImplicitNull => None,
+        Nullable::ExplicitNull => Some(None),
+        Nullable::Some(value) => validator.value(field, check(value)).map(Some),
+    }
+}
+
+impl SongInput {
+    /// The changes to make, after checking that all values are valid.
+    pub fn into_update(self) -> ChordmateResult<SongUpdate> {
+        let mut validator = Validator::new();
+        if let Some(title) = &self.title {
+            validator.not_blank("title", title);
+            validator.max_length("title", title, MAX_NAME_LENGTH);
+        }
+        if let Some(artist) = &self.artist {
+            validator.max_length("artist", artist, MAX_NAME_LENGTH);
+        }
+        if let Some(track) = &self.spotify_track {
+            if !is_spotify_track_id(track) {
+                validator.invalid(
+                    "spotifyTrack",
+                    format!("'{track}' is not a Spotify track id."),
+                );
+            }
+        }
+        let update = SongUpdate {
+            title: self.title,
+            artist: self.artist,
+            spotify_track: self.spotify_track,
+            key: nullable(&mut validator, "key", self.key, |key| {
+                Key::parse(&key)
+                    .map(|key| key.to_string())
+                    .ok_or_else(|| format!("'{key}' is not a key."))
+            }),
+            bpm: nullable(&mut validator, "bpm", self.bpm, |bpm| {
+                in_range("bpm", bpm, 1, MAX_BPM)
+            }),
+            time_signature: nullable(
+                &mut validator,
+                "timeSignature",
+                self.time_signature,
+                |time_signature| {
+                    normalize_time_signature(&time_signature)
+                        .ok_or_else(|| format!("'{time_signature}' is not a time signature."))
+                },
+            ),
+            capo: nullable(&mut validator, "capo", self.capo, |capo| {
+                in_range("capo", capo, 0, MAX_CAPO)
+            }),
+            duration_seconds: nullable(
+                &mut validator,
+                "durationSeconds",
+                self.duration_seconds,
+                |duration| in_range("durationSeconds", duration, 0, MAX_DURATION_SECONDS),
+            ),
+        };
+        validator.finish()?;
+        Ok(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ChordmateError;
+
+    #[test]
+    fn values_are_normalized() {
+        let input = SongInput {
+            title: Some("Wonderwall".into()),
+            key: Nullable::Some("F#m".into()),
+            time_signature: Nullable::Some(" 6 / 8 ".into()),
+            bpm: Nullable::Some(87),
+            capo: Nullable::Some(2),
+            ..SongInput::default()
+        };
+        assert_eq!(
+            input.into_update().unwrap(),
+            SongUpdate {
+                title: Some("Wonderwall".into()),
+                key: Some(Some("F#m".into())),
+                time_signature: Some(Some("6/8".into())),
+                bpm: Some(Some(87)),
+                capo: Some(Some(2)),
+                ..SongUpdate::default()
+            }
+        );
+    }
+
+    #[test]
+    fn explicit_nulls_clear_fields_and_left_out_fields_are_kept() {
+        let input = SongInput {
+            key: Nullable::ExplicitNull,
+            duration_seconds: Nullable::ExplicitNull,
+            ..SongInput::default()
+        };
+        let update = input.into_update().unwrap();
+        assert_eq!(update.key, Some(None));
+        assert_eq!(update.duration_seconds, Some(None));
+        assert_eq!(update.bpm, None);
+        assert_eq!(update.title, None);
+    }
+
+    #[test]
+    fn numbers_must_be_in_range() {
+        for (input, message) in [
+            (
+                SongInput {
+                    bpm: Nullable::Some(0),
+                    ..SongInput::default()
+                },
+                "`bpm` must be between 1 and 400.",
+            ),
+            (
+                SongInput {
+                    capo: Nullable::Some(MAX_CAPO + 1),
+                    ..SongInput::default()
+                },
+                "`capo` must be between 0 and 12.",
+            ),
+            (
+                SongInput {
+                    duration_seconds: Nullable::Some(-1),
+                    ..SongInput::default()
+                },
+                "`durationSeconds` must be between 0 and 86400.",
+            ),
+        ] {
+            assert_eq!(input.into_update().unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn spotify_track_ids_are_checked() {
+        let valid = |id: &str| {
+            SongInput {
+                spotify_track: Some(id.into()),
+                ..SongInput::default()
+            }
+            .into_update()
+            .is_ok()
+        };
+        assert!(valid("4uLU6hMCjMI75M1A2tKUQC"));
+        assert!(valid(""));
+        assert!(!valid("4uLU6hMCjMI75M1A2tKUQ"));
+        assert!(!valid(
+            "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC"
+        ));
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported() {
+        let input = SongInput {
+            title: Some(" ".into()),
+            key: Nullable::Some("H#".into()),
+            time_signature: Nullable::Some("4/5".into()),
+            ..SongInput::default()
+        };
+        assert_eq!(
+            input.into_update(),
+            Err(ChordmateError::Validation(vec![
+                ("title".into(), "`title` must not be empty.".into()),
+                ("key".into(), "'H#' is not a key.".into()),
+                (
+                    "timeSignature".into(),
+                    "'4/5' is not a time signature.".into()
+                ),
+            ]))
+        );
+    }
+}
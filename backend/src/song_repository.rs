@@ -0,0 +1,1030 @@
+//! Where songs are kept. Resolvers load and save songs through a [`SongRepository`] instead of
+//! querying the `songs` table themselves, so the schema can also run without a database.
+
+use crate::auth::User;
+use crate::error::ChordmateResult;
+use crate::song::Song;
+use crate::song_access::{SongAudience, SongPermission, SongRole};
+use crate::song_revision::{self, SongRevision};
+use crate::song_search::{self, SongFilter, SongOrder};
+use crate::tag::Tag;
+use async_trait::async_trait;
+use chrono::Utc;
+use deadpool_postgres::Pool;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tokio_postgres::types::ToSql;
+
+/// Changes to the details of a song. Fields that are `None` keep their value, nullable fields
+/// that are `Some(None)` are cleared.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SongUpdate {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub spotify_track: Option<String>,
+    pub key: Option<Option<String>>,
+    pub bpm: Option<Option<i32>>,
+    pub time_signature: Option<Option<String>>,
+    pub capo: Option<Option<i32>>,
+    pub duration_seconds: Option<Option<i32>>,
+}
+
+impl SongUpdate {
+    pub fn is_empty(&self) -> bool {
+        *self == SongUpdate::default()
+    }
+
+    pub fn apply(&self, song: &mut Song) {
+        fn set<T: Clone>(field: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+        set(&mut song.title, &self.title);
+        set(&mut song.artist, &self.artist);
+        set(&mut song.spotify_track, &self.spotify_track);
+        set(&mut song.key, &self.key);
+        set(&mut song.bpm, &self.bpm);
+        set(&mut song.time_signature, &self.time_signature);
+        set(&mut song.capo, &self.capo);
+        set(&mut song.duration_seconds, &self.duration_seconds);
+    }
+}
+
+#[async_trait]
+pub trait SongRepository: Send + Sync {
+    /// The song with this id, whoever may see it.
+    async fn find(&self, id: i32) -> ChordmateResult<Option<Song>>;
+
+    /// The songs with the given ids that `user_id` may see, in no particular order.
+    async fn find_visible(&self, ids: &[i32], user_id: Option<i32>) -> ChordmateResult<Vec<Song>>;
+
+    /// The role `user_id` has on a song. `None` if the song does not exist, `Some(None)` if the
+    /// user may not see it.
+    async fn role(
+        &self,
+        id: i32,
+        user_id: Option<i32>,
+    ) -> ChordmateResult<Option<Option<SongRole>>>;
+
+    /// Who may see a song, `None` if it does not exist.
+    async fn audience(&self, id: i32) -> ChordmateResult<Option<SongAudience>>;
+
+    /// The songs matching `filter` that `user_id` may see, skipping the first `offset` and
+    /// returning at most `limit`. Their content is left out.
+    async fn search(
+        &self,
+        filter: &SongFilter,
+        user_id: Option<i32>,
+        offset: i64,
+        limit: Option<i64>,
+    ) -> ChordmateResult<Vec<Song>>;
+
+    /// How many songs `search` finds on all pages together.
+    async fn count(&self, filter: &SongFilter, user_id: Option<i32>) -> ChordmateResult<i64>;
+
+    /// Adds a song and returns its id. Content that is not empty is kept as the first revision.
+    async fn create(
+        &self,
+        owner_id: i32,
+        title: &str,
+        artist: &str,
+        content: &str,
+    ) -> ChordmateResult<i32>;
+
+    /// Returns `false` if there is no song with this id.
+    async fn update(&self, id: i32, update: &SongUpdate) -> ChordmateResult<bool>;
+
+    /// Replaces the content of a song and keeps it as a revision by `author_id`, changing the
+    /// details in `update` at the same time. Returns `false` if there is no song with this id.
+    async fn save_content(
+        &self,
+        id: i32,
+        content: &str,
+        update: &SongUpdate,
+        author_id: Option<i32>,
+    ) -> ChordmateResult<bool>;
+
+    /// Returns `false` if there was no song with this id.
+    async fn delete(&self, id: i32) -> ChordmateResult<bool>;
+
+    /// Every revision of a song, the most recent first.
+    async fn revisions(&self, song_id: i32) -> ChordmateResult<Vec<SongRevision>>;
+
+    /// A revision of a song, `None` if the song has no revision with this id.
+    async fn revision(
+        &self,
+        song_id: i32,
+        revision_id: i32,
+    ) -> ChordmateResult<Option<SongRevision>>;
+
+    /// The names of the tags of a song, in alphabetical order.
+    async fn tags(&self, song_id: i32) -> ChordmateResult<Vec<String>>;
+
+    /// Every tag of a song `user_id` may see, with how many of those songs have it, most used
+    /// first.
+    async fn tag_counts(&self, user_id: Option<i32>) -> ChordmateResult<Vec<Tag>>;
+
+    /// Tags a song. Tag names are not case-sensitive: a tag someone used before keeps its
+    /// spelling.
+    async fn add_tag(&self, song_id: i32, name: &str) -> ChordmateResult<()>;
+
+    /// Returns `false` if the song did not have the tag. Tags no song has anymore are forgotten.
+    async fn remove_tag(&self, song_id: i32, name: &str) -> ChordmateResult<bool>;
+
+    /// The user with this id, `None` if there is no such account.
+    async fn user(&self, id: i32) -> ChordmateResult<Option<User>>;
+
+    async fn user_named(&self, username: &str) -> ChordmateResult<Option<User>>;
+
+    /// The users a song is shared with, by username.
+    async fn permissions(&self, song_id: i32) -> ChordmateResult<Vec<SongPermission>>;
+
+    /// Gives a user a role on a song, replacing the role they had.
+    async fn share(&self, song_id: i32, user_id: i32, role: SongRole) -> ChordmateResult<()>;
+
+    /// Returns `false` if the song was not shared with the user.
+    async fn unshare(&self, song_id: i32, user_id: i32) -> ChordmateResult<bool>;
+}
+
+/// Keeps songs in the `songs` table, together with their revisions.
+#[derive(Clone)]
+pub struct PostgresSongRepository {
+    pool: Pool,
+}
+
+type Parameters = Vec<Box<dyn ToSql + Sync + Send>>;
+
+/// Every column of a song but its content, which can be far larger than the rest together.
+const LIST_COLUMNS: &str = "songs.id, songs.title, songs.artist, songs.spotify_track, songs.key, \
+     songs.bpm, songs.time_signature, songs.capo, songs.duration_seconds, songs.owner_id";
+
+fn parameter_refs(parameters: &Parameters) -> Vec<&(dyn ToSql + Sync)> {
+    parameters
+        .iter()
+        .map(|parameter| parameter.as_ref() as &(dyn ToSql + Sync))
+        .collect()
+}
+
+/// The `WHERE` clause selecting the songs of `filter` that `user_id` may see, the parameters it
+/// refers to and which of them is the search query, if there is one.
+fn condition(filter: &SongFilter, user_id: Option<i32>) -> (String, Parameters, Option<usize>) {
+    let mut parameters: Parameters = vec![Box::new(user_id)];
+    let mut conditions = vec![String::from("song_role(songs.id, $1) IS NOT NULL")];
+    let mut search = None;
+    if let Some(query) = filter.search.as_deref().and_then(song_search::to_tsquery) {
+        parameters.push(Box::new(query));
+        search = Some(parameters.len());
+        conditions.push(format!(
+            "songs.search_vector @@ to_tsquery('simple', ${})",
+            parameters.len()
+        ));
+    }
+    if let Some(artist) = &filter.artist {
+        parameters.push(Box::new(artist.trim().to_string()));
+        conditions.push(format!(
+            "lower(songs.artist) = lower(${})",
+            parameters.len()
+        ));
+    }
+    if let Some(tag) = &filter.tag {
+        parameters.push(Box::new(tag.trim().to_string()));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM song_tags JOIN tags ON tags.id = song_tags.tag_id \
+             WHERE song_tags.song_id = songs.id AND lower(tags.name) = lower(${}))",
+            parameters.len()
+        ));
+    }
+    if let Some(key) = filter.normalized_key() {
+        parameters.push(Box::new(key));
+        conditions.push(format!("songs.key = ${}", parameters.len()));
+    }
+    (conditions.join(" AND "), parameters, search)
+}
+
+fn order(filter: &SongFilter, search: Option<usize>) -> String {
+    match (filter.order_by.unwrap_or(SongOrder::Relevance), search) {
+        (SongOrder::Relevance, Some(search)) => {
+            format!("ts_rank(songs.search_vector, to_tsquery('simple', ${search})) DESC, songs.id")
+        }
+        (SongOrder::Relevance | SongOrder::Title, _) => {
+            String::from("lower(songs.title), songs.id")
+        }
+        (SongOrder::Artist, _) => String::from("lower(songs.artist), lower(songs.title), songs.id"),
+        (SongOrder::Newest, _) => String::from("songs.id DESC"),
+    }
+}
+
+/// The columns to set for an update, with their new values.
+fn assignments(update: &SongUpdate) -> Vec<(&'static str, Box<dyn ToSql + Sync + Send>)> {
+    let mut assignments: Vec<(&'static str, Box<dyn ToSql + Sync + Send>)> = Vec::new();
+    if let Some(title) = &update.title {
+        assignments.push(("title", Box::new(title.clone())));
+    }
+    if let Some(artist) = &update.artist {
+        assignments.push(("artist", Box::new(artist.clone())));
+    }
+    if let Some(track) = &update.spotify_track {
+        assignments.push(("spotify_track", Box::new(track.clone())));
+    }
+    if let Some(key) = &update.key {
+        assignments.push(("key", Box::new(key.clone())));
+    }
+    if let Some(bpm) = update.bpm {
+        assignments.push(("bpm", Box::new(bpm)));
+    }
+    if let Some(time_signature) = &update.time_signature {
+        assignments.push(("time_signature", Box::new(time_signature.clone())));
+    }
+    if let Some(capo) = update.capo {
+        assignments.push(("capo", Box::new(capo)));
+    }
+    if let Some(duration) = update.duration_seconds {
+        assignments.push(("duration_seconds", Box::new(duration)));
+    }
+    assignments
+}
+
+/// The `SET` list of an update, with the values of the columns added to `parameters`.
+fn set_columns(
+    assignments: Vec<(&'static str, Box<dyn ToSql + Sync + Send>)>,
+    parameters: &mut Parameters,
+) -> String {
+    let mut columns = Vec::new();
+    for (column, value) in assignments {
+        parameters.push(value);
+        columns.push(format!("{column} = ${}", parameters.len()));
+    }
+    columns.join(", ")
+}
+
+impl PostgresSongRepository {
+    pub fn new(pool: Pool) -> PostgresSongRepository {
+        PostgresSongRepository { pool }
+    }
+
+    /// Fills in the search index of songs saved before there was one.
+    pub async fn index_missing_lyrics(&self) -> ChordmateResult<u64> {
+        let client = self.pool.get().await?;
+        let rows = client
+            .query("SELECT id, content FROM songs WHERE lyrics IS NULL", &[])
+            .await?;
+        let statement = client
+            .prepare("UPDATE songs SET lyrics = $2 WHERE id = $1")
+            .await?;
+        for row in &rows {
+            let id: i32 = row.try_get("id")?;
+            let content: String = row.try_get("content")?;
+            client
+                .execute(&statement, &[&id, &song_search::lyrics_index(&content)])
+                .await?;
+        }
+        Ok(rows.len() as u64)
+    }
+}
+
+#[async_trait]
+impl SongRepository for PostgresSongRepository {
+    async fn find(&self, id: i32) -> ChordmateResult<Option<Song>> {
+        let client = self.pool.get().await?;
+        let statement = client.prepare("SELECT * FROM songs WHERE id = $1").await?;
+        let row = client.query_opt(&statement, &[&id]).await?;
+        Ok(row.as_ref().map(Song::from_row).transpose()?)
+    }
+
+    async fn find_visible(&self, ids: &[i32], user_id: Option<i32>) -> ChordmateResult<Vec<Song>> {
+        let client = self.pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT * FROM songs WHERE id = ANY($1) AND song_role(id, $2) IS NOT NULL",
+                &[&ids, &user_id],
+            )
+            .await?;
+        Ok(rows.iter().map(Song::from_row).collect::<Result<_, _>>()?)
+    }
+
+    async fn role(
+        &self,
+        id: i32,
+        user_id: Option<i32>,
+    ) -> ChordmateResult<Option<Option<SongRole>>> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT song_role(id, $2) AS role FROM songs WHERE id = $1",
+                &[&id, &user_id],
+            )
+            .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let role: Option<String> = row.try_get("role")?;
+        Ok(Some(role.as_deref().and_then(SongRole::parse)))
+    }
+
+    async fn audience(&self, id: i32) -> ChordmateResult<Option<SongAudience>> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT owner_id, ARRAY(SELECT user_id FROM song_permissions WHERE song_id = $1) \
+                 AS shared_with FROM songs WHERE id = $1",
+                &[&id],
+            )
+            .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let owner_id: Option<i32> = row.try_get("owner_id")?;
+        let mut users: Vec<i32> = row.try_get("shared_with")?;
+        Ok(Some(match owner_id {
+            Some(owner_id) => {
+                users.push(owner_id);
+                SongAudience::Users(users)
+            }
+            None => SongAudience::Everyone,
+        }))
+    }
+
+    async fn search(
+        &self,
+        filter: &SongFilter,
+        user_id: Option<i32>,
+        offset: i64,
+        limit: Option<i64>,
+    ) -> ChordmateResult<Vec<Song>> {
+        let (condition, mut parameters, search) = condition(filter, user_id);
+        parameters.push(Box::new(offset));
+        parameters.push(Box::new(limit));
+        let sql = format!(
+            "SELECT {LIST_COLUMNS} FROM songs WHERE {condition} ORDER BY {} OFFSET ${} LIMIT ${}",
+            order(filter, search),
+            parameters.len() - 1,
+            parameters.len()
+        );
+        let client = self.pool.get().await?;
+        let rows = client.query(&sql, &parameter_refs(&parameters)).await?;
+        Ok(rows.iter().map(Song::from_row).collect::<Result<_, _>>()?)
+    }
+
+    async fn count(&self, filter: &SongFilter, user_id: Option<i32>) -> ChordmateResult<i64> {
+        let (condition, parameters, _) = condition(filter, user_id);
+        let client = self.pool.get().await?;
+        let row = client
+            .query_one(
+                &format!("SELECT count(*) AS count FROM songs WHERE {condition}"),
+                &parameter_refs(&parameters),
+            )
+            .await?;
+        Ok(row.try_get("count")?)
+    }
+
+    async fn create(
+        &self,
+        owner_id: i32,
+        title: &str,
+        artist: &str,
+        content: &str,
+    ) -> ChordmateResult<i32> {
+        let mut client = self.pool.get().await?;
+        let transaction = client.transaction().await?;
+        let statement = transaction
+            .prepare(
+                "INSERT INTO songs (title, artist, content, spotify_track, owner_id, lyrics) VALUES ($1, $2, $3, '', $4, $5) RETURNING id"
+            )
+            .await?;
+        let row = transaction
+            .query_one(
+                &statement,
+                &[
+                    &title,
+                    &artist,
+                    &content,
+                    &owner_id,
+                    &song_search::lyrics_index(content),
+                ],
+            )
+            .await?;
+        let id: i32 = row.try_get("id")?;
+        if !content.is_empty() {
+            song_revision::record(&transaction, id, content, Some(owner_id)).await?;
+        }
+        transaction.commit().await?;
+        Ok(id)
+    }
+
+    async fn update(&self, id: i32, update: &SongUpdate) -> ChordmateResult<bool> {
+        let assignments = assignments(update);
+        if assignments.is_empty() {
+            return Ok(self.find(id).await?.is_some());
+        }
+        let mut parameters: Parameters = vec![Box::new(id)];
+        let columns = set_columns(assignments, &mut parameters);
+        let client = self.pool.get().await?;
+        let statement = client
+            .prepare(&format!("UPDATE songs SET {columns} WHERE id = $1;"))
+            .await?;
+        Ok(client
+            .execute(&statement, &parameter_refs(&parameters))
+            .await?
+            > 0)
+    }
+
+    async fn save_content(
+        &self,
+        id: i32,
+        content: &str,
+        update: &SongUpdate,
+        author_id: Option<i32>,
+    ) -> ChordmateResult<bool> {
+        let mut values: Vec<(&'static str, Box<dyn ToSql + Sync + Send>)> = vec![
+            ("content", Box::new(content.to_string())),
+            ("lyrics", Box::new(song_search::lyrics_index(content))),
+        ];
+        values.extend(assignments(update));
+        let mut parameters: Parameters = vec![Box::new(id)];
+        let columns = set_columns(values, &mut parameters);
+        let mut client = self.pool.get().await?;
+        let transaction = client.transaction().await?;
+        let statement = transaction
+            .prepare(&format!("UPDATE songs SET {columns} WHERE id = $1;"))
+            .await?;
+        let updated = transaction
+            .execute(&statement, &parameter_refs(&parameters))
+            .await?;
+        if updated == 0 {
+            return Ok(false);
+        }
+        song_revision::record(&transaction, id, content, author_id).await?;
+        transaction.commit().await?;
+        Ok(true)
+    }
+
+    async fn delete(&self, id: i32) -> ChordmateResult<bool> {
+        let client = self.pool.get().await?;
+        let statement = client.prepare("DELETE FROM songs WHERE id = $1;").await?;
+        Ok(client.execute(&statement, &[&id]).await? > 0)
+    }
+
+    async fn revisions(&self, song_id: i32) -> ChordmateResult<Vec<SongRevision>> {
+        let client = self.pool.get().await?;
+        Ok(SongRevision::for_song(&client, song_id).await?)
+    }
+
+    async fn revision(
+        &self,
+        song_id: i32,
+        revision_id: i32,
+    ) -> ChordmateResult<Option<SongRevision>> {
+        let client = self.pool.get().await?;
+        Ok(SongRevision::find(&client, song_id, revision_id).await?)
+    }
+
+    async fn tags(&self, song_id: i32) -> ChordmateResult<Vec<String>> {
+        let client = self.pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT tags.name FROM song_tags JOIN tags ON tags.id = song_tags.tag_id \
+                 WHERE song_tags.song_id = $1 ORDER BY lower(tags.name)",
+                &[&song_id],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| row.try_get("name"))
+            .collect::<Result<_, _>>()?)
+    }
+
+    async fn tag_counts(&self, user_id: Option<i32>) -> ChordmateResult<Vec<Tag>> {
+        let client = self.pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT tags.name, count(*) AS song_count FROM tags \
+                 JOIN song_tags ON song_tags.tag_id = tags.id \
+                 WHERE song_role(song_tags.song_id, $1) IS NOT NULL \
+                 GROUP BY tags.id, tags.name ORDER BY song_count DESC, lower(tags.name)",
+                &[&user_id],
+            )
+            .await?;
+        Ok(rows.iter().map(Tag::from_row).collect::<Result<_, _>>()?)
+    }
+
+    async fn add_tag(&self, song_id: i32, name: &str) -> ChordmateResult<()> {
+        let mut client = self.pool.get().await?;
+        let transaction = client.transaction().await?;
+        let statement = transaction
+            .prepare(
+                "WITH inserted AS (\
+                     INSERT INTO tags (name) VALUES ($1) ON CONFLICT ((lower(name))) DO NOTHING RETURNING id\
+                 ) \
+                 SELECT id FROM inserted UNION ALL SELECT id FROM tags WHERE lower(name) = lower($1);",
+            )
+            .await?;
+        let tag_id: i32 = transaction
+            .query_one(&statement, &[&name])
+            .await?
+            .try_get("id")?;
+        let statement = transaction
+            .prepare(
+                "INSERT INTO song_tags (song_id, tag_id) VALUES ($1, $2) ON CONFLICT DO NOTHING;",
+            )
+            .await?;
+        transaction
+            .execute(&statement, &[&song_id, &tag_id])
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn remove_tag(&self, song_id: i32, name: &str) -> ChordmateResult<bool> {
+        let mut client = self.pool.get().await?;
+        let transaction = client.transaction().await?;
+        let statement = transaction
+            .prepare(
+                "DELETE FROM song_tags USING tags \
+                 WHERE song_tags.tag_id = tags.id AND song_tags.song_id = $1 AND lower(tags.name) = lower($2);",
+            )
+            .await?;
+        let removed = transaction.execute(&statement, &[&song_id, &name]).await? > 0;
+        // Forget tags that are not used anymore.
+        let statement = transaction
+            .prepare(
+                "DELETE FROM tags WHERE lower(name) = lower($1) \
+                 AND NOT EXISTS (SELECT 1 FROM song_tags WHERE song_tags.tag_id = tags.id);",
+            )
+            .await?;
+        transaction.execute(&statement, &[&name]).await?;
+        transaction.commit().await?;
+        Ok(removed)
+    }
+
+    async fn user(&self, id: i32) -> ChordmateResult<Option<User>> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt("SELECT id, username FROM users WHERE id = $1", &[&id])
+            .await?;
+        Ok(row.as_ref().map(User::from_row).transpose()?)
+    }
+
+    async fn user_named(&self, username: &str) -> ChordmateResult<Option<User>> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                "SELECT id, username FROM users WHERE username = $1",
+                &[&username],
+            )
+            .await?;
+        Ok(row.as_ref().map(User::from_row).transpose()?)
+    }
+
+    async fn permissions(&self, song_id: i32) -> ChordmateResult<Vec<SongPermission>> {
+        let client = self.pool.get().await?;
+        let rows = client
+            .query(
+                "SELECT users.id, users.username, song_permissions.role FROM song_permissions \
+                 JOIN users ON users.id = song_permissions.user_id \
+                 WHERE song_permissions.song_id = $1 ORDER BY users.username",
+                &[&song_id],
+            )
+            .await?;
+        rows.iter()
+            .map(|row| {
+                let role: String = row.try_get("role")?;
+                Ok(SongPermission {
+                    user: User::from_row(row)?,
+                    role: SongRole::parse(&role).unwrap_or(SongRole::Viewer),
+                })
+            })
+            .collect()
+    }
+
+    async fn share(&self, song_id: i32, user_id: i32, role: SongRole) -> ChordmateResult<()> {
+        let client = self.pool.get().await?;
+        client
+            .execute(
+                "INSERT INTO song_permissions (song_id, user_id, role) VALUES ($1, $2, $3) \
+                 ON CONFLICT (song_id, user_id) DO UPDATE SET role = EXCLUDED.role;",
+                &[&song_id, &user_id, &role.as_str()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn unshare(&self, song_id: i32, user_id: i32) -> ChordmateResult<bool> {
+        let client = self.pool.get().await?;
+        Ok(client
+            .execute(
+                "DELETE FROM song_permissions WHERE song_id = $1 AND user_id = $2;",
+                &[&song_id, &user_id],
+            )
+            .await?
+            > 0)
+    }
+}
+
+/// Keeps songs in memory, for running the schema without a database, e.g. in tests.
+///
+/// There are no accounts to sign up for: songs can only be shared with users that were added with
+/// [`InMemorySongRepository::add_user`].
+#[derive(Default)]
+pub struct InMemorySongRepository {
+    state: Mutex<InMemoryState>,
+}
+
+#[derive(Default)]
+struct InMemoryState {
+    songs: BTreeMap<i32, Song>,
+    /// Ids are not reused after a song is deleted, like in the database.
+    last_id: i32,
+    /// The revisions of every song by song id, the oldest first.
+    revisions: BTreeMap<i32, Vec<SongRevision>>,
+    last_revision_id: i32,
+    /// The names of the tags of every song by song id.
+    tags: BTreeMap<i32, Vec<String>>,
+    users: BTreeMap<i32, User>,
+    /// The roles of the users a song is shared with, by song id and user id.
+    permissions: BTreeMap<i32, BTreeMap<i32, SongRole>>,
+}
+
+impl InMemoryState {
+    /// Keeps a copy of the content a song was just saved with, like [`song_revision::record`].
+    fn record(&mut self, song_id: i32, content: &str, author_id: Option<i32>) {
+        let author = author_id.and_then(|id| self.users.get(&id).cloned());
+        let revisions = self.revisions.entry(song_id).or_default();
+        if revisions
+            .last()
+            .is_some_and(|latest| latest.content == content)
+        {
+            return;
+        }
+        self.last_revision_id += 1;
+        revisions.push(SongRevision {
+            id: self.last_revision_id,
+            content: content.to_string(),
+            author,
+            created_at: Utc::now(),
+        });
+    }
+
+    /// The same rules as the `song_role` function of the database.
+    fn role(&self, song: &Song, user_id: Option<i32>) -> Option<SongRole> {
+        match (song.owner_id, user_id) {
+            (None, None) => Some(SongRole::Viewer),
+            (None, Some(_)) => Some(SongRole::Editor),
+            (Some(owner_id), Some(user_id)) if owner_id == user_id => Some(SongRole::Owner),
+            (Some(_), Some(user_id)) => self
+                .permissions
+                .get(&song.id)
+                .and_then(|permissions| permissions.get(&user_id))
+                .copied(),
+            (Some(_), None) => None,
+        }
+    }
+
+    fn has_tag(&self, song_id: i32, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.tags
+            .get(&song_id)
+            .is_some_and(|tags| tags.iter().any(|tag| tag.to_lowercase() == name))
+    }
+}
+
+/// How well a song matches the words of a search, weighing title, artist and lyrics like the
+/// search vector of the `songs` table does. `None` unless every word is found.
+fn relevance(song: &Song, words: &[String]) -> Option<f32> {
+    let fields = [
+        (song_search::search_words(&song.title), 1.0),
+        (song_search::search_words(&song.artist), 0.4),
+        (
+            song_search::search_words(&song_search::lyrics_index(song.loaded_content())),
+            0.2,
+        ),
+    ];
+    words
+        .iter()
+        .map(|word| {
+            fields
+                .iter()
+                .filter(|(field, _)| field.iter().any(|w| w.starts_with(word.as_str())))
+                .map(|(_, weight)| *weight)
+                .reduce(f32::max)
+        })
+        .sum()
+}
+
+impl InMemorySongRepository {
+    pub fn new() -> InMemorySongRepository {
+        InMemorySongRepository::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, InMemoryState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Makes a user known, so songs can be shared with them and their revisions have an author.
+    pub fn add_user(&self, user: User) {
+        self.state().users.insert(user.id, user);
+    }
+
+    /// The songs `search` finds, in order.
+    fn matching(&self, filter: &SongFilter, user_id: Option<i32>) -> Vec<Song> {
+        let words = filter
+            .search
+            .as_deref()
+            .map(song_search::search_words)
+            .unwrap_or_default();
+        let key = filter.normalized_key();
+        let state = self.state();
+        let mut songs: Vec<(Song, Option<f32>)> = state
+            .songs
+            .values()
+            .filter(|song| state.role(song, user_id).is_some())
+            .filter(|song| {
+                filter
+                    .artist
+                    .as_ref()
+                    .is_none_or(|artist| song.artist.to_lowercase() == artist.trim().to_lowercase())
+            })
+            .filter(|song| {
+                filter
+                    .tag
+                    .as_ref()
+                    .is_none_or(|tag| state.has_tag(song.id, tag.trim()))
+            })
+            .filter(|song| key.is_none() || song.key == key)
+            .filter_map(|song| {
+                if words.is_empty() {
+                    Some((song.clone(), None))
+                } else {
+                    relevance(song, &words).map(|relevance| (song.clone(), Some(relevance)))
+                }
+            })
+            .collect();
+        let lower = |text: &str| text.to_lowercase();
+        match (
+            filter.order_by.unwrap_or(SongOrder::Relevance),
+            words.is_empty(),
+        ) {
+            (SongOrder::Relevance, false) => songs.sort_by(|(a, a_relevance), (b, b_relevance)| {
+                b_relevance
+                    .unwrap_or_default()
+                    .total_cmp(&a_relevance.unwrap_or_default())
+                    .then(a.id.cmp(&b.id))
+            }),
+            (SongOrder::Relevance | SongOrder::Title, _) => {
+                songs.sort_by_key(|(song, _)| (lower(&song.title), song.id))
+            }
+            (SongOrder::Artist, _) => {
+                songs.sort_by_key(|(song, _)| (lower(&song.artist), lower(&song.title), song.id))
+            }
+            (SongOrder::Newest, _) => songs.sort_by_key(|(song, _)| -song.id),
+        }
+        songs.into_iter().map(|(song, _)| song).collect()
+    }
+}
+
+#[async_trait]
+impl SongRepository for InMemorySongRepository {
+    async fn find(&self, id: i32) -> ChordmateResult<Option<Song>> {
+        Ok(self.state().songs.get(&id).cloned())
+    }
+
+    async fn find_visible(&self, ids: &[i32], user_id: Option<i32>) -> ChordmateResult<Vec<Song>> {
+        let state = self.state();
+        Ok(ids
+            .iter()
+            .filter_map(|id| state.songs.get(id))
+            .filter(|song| state.role(song, user_id).is_some())
+            .cloned()
+            .collect())
+    }
+
+    async fn role(
+        &self,
+        id: i32,
+        user_id: Option<i32>,
+    ) -> ChordmateResult<Option<Option<SongRole>>> {
+        let state = self.state();
+        Ok(state.songs.get(&id).map(|song| state.role(song, user_id)))
+    }
+
+    async fn audience(&self, id: i32) -> ChordmateResult<Option<SongAudience>> {
+        let state = self.state();
+        Ok(state.songs.get(&id).map(|song| match song.owner_id {
+            Some(owner_id) => {
+                let permissions = state.permissions.get(&id).into_iter().flatten();
+                let mut users: Vec<i32> = permissions.map(|(user_id, _)| *user_id).collect();
+                users.push(owner_id);
+                SongAudience::Users(users)
+            }
+            None => SongAudience::Everyone,
+        }))
+    }
+
+    async fn search(
+        &self,
+        filter: &SongFilter,
+        user_id: Option<i32>,
+        offset: i64,
+        limit: Option<i64>,
+    ) -> ChordmateResult<Vec<Song>> {
+        Ok(self
+            .matching(filter, user_id)
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit.map_or(usize::MAX, |limit| limit as usize))
+            .map(|song| Song {
+                content: None,
+                ..song
+            })
+            .collect())
+    }
+
+    async fn count(&self, filter: &SongFilter, user_id: Option<i32>) -> ChordmateResult<i64> {
+        Ok(self.matching(filter, user_id).len() as i64)
+    }
+
+    async fn create(
+        &self,
+        owner_id: i32,
+        title: &str,
+        artist: &str,
+        content: &str,
+    ) -> ChordmateResult<i32> {
+        let mut state = self.state();
+        state.last_id += 1;
+        let id = state.last_id;
+        state.songs.insert(
+            id,
+            Song {
+                id,
+                title: title.to_string(),
+                artist: artist.to_string(),
+                spotify_track: String::new(),
+                content: Some(content.to_string()),
+                key: None,
+                bpm: None,
+                time_signature: None,
+                capo: None,
+                duration_seconds: None,
+                owner_id: Some(owner_id),
+            },
+        );
+        if !content.is_empty() {
+            state.record(id, content, Some(owner_id));
+        }
+        Ok(id)
+    }
+
+    async fn update(&self, id: i32, update: &SongUpdate) -> ChordmateResult<bool> {
+        let mut state = self.state();
+        let Some(song) = state.songs.get_mut(&id) else {
+            return Ok(false);
+        };
+        update.apply(song);
+        Ok(true)
+    }
+
+    async fn save_content(
+        &self,
+        id: i32,
+        content: &str,
+        update: &SongUpdate,
+        author_id: Option<i32>,
+    ) -> ChordmateResult<bool> {
+        let mut state = self.state();
+        let Some(song) = state.songs.get_mut(&id) else {
+            return Ok(false);
+        };
+        song.content = Some(content.to_string());
+        update.apply(song);
+        state.record(id, content, author_id);
+        Ok(true)
+    }
+
+    async fn delete(&self, id: i32) -> ChordmateResult<bool> {
+        let mut state = self.state();
+        state.revisions.remove(&id);
+        state.tags.remove(&id);
+        state.permissions.remove(&id);
+        Ok(state.songs.remove(&id).is_some())
+    }
+
+    async fn revisions(&self, song_id: i32) -> ChordmateResult<Vec<SongRevision>> {
+        let state = self.state();
+        let revisions = state.revisions.get(&song_id).into_iter().flatten();
+        Ok(revisions.rev().cloned().collect())
+    }
+
+    async fn revision(
+        &self,
+        song_id: i32,
+        revision_id: i32,
+    ) -> ChordmateResult<Option<SongRevision>> {
+        let state = self.state();
+        let mut revisions = state.revisions.get(&song_id).into_iter().flatten();
+        Ok(revisions
+            .find(|revision| revision.id == revision_id)
+            .cloned())
+    }
+    async fn tags(&self, song_id: i32) -> ChordmateResult<Vec<String>> {
+        let mut tags = self.state().tags.get(&song_id).cloned().unwrap_or_default();
+        tags.sort_by_key(|tag| tag.to_lowercase());
+        Ok(tags)
+    }
+
+    async fn tag_counts(&self, user_id: Option<i32>) -> ChordmateResult<Vec<Tag>> {
+        let state = self.state();
+        let mut counts: BTreeMap<String, Tag> = BTreeMap::new();
+        for (song_id, tags) in &state.tags {
+            let song = &state.songs[song_id];
+            if state.role(song, user_id).is_none() {
+                continue;
+            }
+            for name in tags {
+                counts
+                    .entry(name.to_lowercase())
+                    .or_insert_with(|| Tag {
+                        name: name.clone(),
+                        song_count: 0,
+                    })
+                    .song_count += 1;
+            }
+        }
+        let mut tags: Vec<Tag> = counts.into_values().collect();
+        tags.sort_by_key(|tag| (-tag.song_count, tag.name.to_lowercase()));
+        Ok(tags)
+    }
+
+    async fn add_tag(&self, song_id: i32, name: &str) -> ChordmateResult<()> {
+        let mut state = self.state();
+        if state.has_tag(song_id, name) {
+            return Ok(());
+        }
+        let lower = name.to_lowercase();
+        let name = state
+            .tags
+            .values()
+            .flatten()
+            .find(|tag| tag.to_lowercase() == lower)
+            .cloned()
+            .unwrap_or_else(|| name.to_string());
+        state.tags.entry(song_id).or_default().push(name);
+        Ok(())
+    }
+
+    async fn remove_tag(&self, song_id: i32, name: &str) -> ChordmateResult<bool> {
+        let mut state = self.state();
+        let name = name.to_lowercase();
+        let Some(tags) = state.tags.get_mut(&song_id) else {
+            return Ok(false);
+        };
+        let count = tags.len();
+        tags.retain(|tag| tag.to_lowercase() != name);
+        Ok(tags.len() < count)
+    }
+
+    async fn user(&self, id: i32) -> ChordmateResult<Option<User>> {
+        Ok(self.state().users.get(&id).cloned())
+    }
+
+    async fn user_named(&self, username: &str) -> ChordmateResult<Option<User>> {
+        let state = self.state();
+        let mut users = state.users.values();
+        Ok(users.find(|user| user.username == username).cloned())
+    }
+
+    async fn permissions(&self, song_id: i32) -> ChordmateResult<Vec<SongPermission>> {
+        let state = self.state();
+        let mut permissions: Vec<SongPermission> = state
+            .permissions
+            .get(&song_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|(user_id, role)| {
+                state.users.get(user_id).map(|user| SongPermission {
+                    user: user.clone(),
+                    role: *role,
+                })
+            })
+            .collect();
+        permissions.sort_by(|a, b| a.user.username.cmp(&b.user.username));
+        Ok(permissions)
+    }
+
+    async fn share(&self, song_id: i32, user_id: i32, role: SongRole) -> ChordmateResult<()> {
+        let mut state = self.state();
+        if state.songs.contains_key(&song_id) {
+            let permissions = state.permissions.entry(song_id).or_default();
+            permissions.insert(user_id, role);
+        }
+        Ok(())
+    }
+
+    async fn unshare(&self, song_id: i32, user_id: i32) -> ChordmateResult<bool> {
+        let mut state = self.state();
+        let permissions = state.permissions.get_mut(&song_id);
+        Ok(permissions.is_some_and(|permissions| permissions.remove(&user_id).is_some()))
+    }
+}
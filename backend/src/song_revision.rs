@@ -0,0 +1,107 @@
+//! The history of a song's content. Every save keeps a copy, so no version is ever lost.
+
+use crate::auth::{Context, User};
+use chrono::{DateTime, SecondsFormat, Utc};
+use juniper::graphql_object;
+use tokio_postgres::{Client, Error, Row, Transaction};
+
+#[derive(Clone, Debug)]
+pub struct SongRevision {
+    pub id: i32,
+    pub content: String,
+    pub author: Option<User>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[graphql_object(context = Context)]
+impl SongRevision {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// `None` if the revision predates accounts or its author's account was deleted.
+    fn author(&self) -> Option<&User> {
+        self.author.as_ref()
+    }
+
+    /// When the revision was saved, in RFC 3339 format, e.g. `2024-03-01T12:30:00Z`.
+    fn created_at(&self) -> String {
+        self.created_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
+
+/// Selects the columns [`SongRevision::from_row`] needs. To be completed with a `WHERE` clause.
+const SELECT_REVISIONS: &str = "SELECT r.id, r.content, r.created_at, \
+     u.id AS author_id, u.username AS author_username \
+     FROM song_revisions r LEFT JOIN users u ON u.id = r.author_id";
+
+impl SongRevision {
+    pub fn from_row(row: &Row) -> Result<SongRevision, Error> {
+        let author_id: Option<i32> = row.try_get("author_id")?;
+        Ok(SongRevision {
+            id: row.try_get("id")?,
+            content: row.try_get("content")?,
+            author: author_id
+                .map(|id| {
+                    Ok::<_, Error>(User {
+                        id,
+                        username: row.try_get("author_username")?,
+                    })
+                })
+                .transpose()?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+
+    /// Every revision of a song, the most recent first.
+    pub async fn for_song(client: &Client, song_id: i32) -> Result<Vec<SongRevision>, Error> {
+        client
+            .query(
+                &format!("{SELECT_REVISIONS} WHERE r.song_id = $1 ORDER BY r.id DESC"),
+                &[&song_id],
+            )
+            .await?
+            .iter()
+            .map(SongRevision::from_row)
+            .collect()
+    }
+
+    pub async fn find(
+        client: &Client,
+        song_id: i32,
+        revision_id: i32,
+    ) -> Result<Option<SongRevision>, Error> {
+        client
+            .query_opt(
+                &format!("{SELECT_REVISIONS} WHERE r.song_id = $1 AND r.id = $2"),
+                &[&song_id, &revision_id],
+            )
+            .await?
+            .as_ref()
+            .map(SongRevision::from_row)
+            .transpose()
+    }
+}
+
+/// Keeps a copy of the content a song was just saved with, unless it is the same as the content
+/// of the latest revision.
+pub async fn record(
+    transaction: &Transaction<'_>,
+    song_id: i32,
+    content: &str,
+    author_id: Option<i32>,
+) -> Result<(), Error> {
+    transaction
+        .execute(
+            "INSERT INTO song_revisions (song_id, content, author_id) SELECT $1, $2, $3 \
+             WHERE $2 IS DISTINCT FROM \
+             (SELECT content FROM song_revisions WHERE song_id = $1 ORDER BY id DESC LIMIT 1)",
+            &[&song_id, &content, &author_id],
+        )
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,165 @@
+//! Finding songs by their title, artist and lyrics. The searching itself is done by the
+//! [`SongRepository`](crate::song_repository::SongRepository), in Postgres with full-text search.
+
+use crate::chord::Key;
+use crate::error::ChordmateResult;
+use crate::song_content::ParsedSong;
+use crate::validation;
+use juniper::GraphQLEnum;
+
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SongOrder {
+    /// Best matches of the search first. Sorts by title if there is no search.
+    Relevance,
+    Title,
+    Artist,
+    Newest,
+}
+
+/// Which songs to list and in what order. Only songs the calling user may see are ever listed.
+#[derive(Clone, Debug, Default)]
+pub struct SongFilter {
+    pub search: Option<String>,
+    /// Matches the artist exactly, ignoring case.
+    pub artist: Option<String>,
+    /// Only songs with this tag, ignoring case.
+    pub tag: Option<String>,
+    /// Only songs written in this key, e.g. `Am`.
+    pub key: Option<String>,
+    pub order_by: Option<SongOrder>,
+}
+
+/// The lowercase words of what a user typed, or of a text to search in.
+pub fn search_words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Turns what a user typed into a query for `to_tsquery` that finds songs containing all words,
+/// the last one possibly only partly typed. `None` if there is nothing to search for.
+pub fn to_tsquery(search: &str) -> Option<String> {
+    let words: Vec<String> = search_words(search)
+        .iter()
+        .map(|word| format!("{word}:*"))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" & "))
+}
+
+/// The text the lyrics of a song are searched by.
+pub fn lyrics_index(content: &str) -> String {
+    ParsedSong::from_html(content).lyrics()
+}
+
+impl SongFilter {
+    /// The key to list songs in, written the way keys are stored, so "Amin" finds songs in "Am".
+    pub fn normalized_key(&self) -> Option<String> {
+        let key = self.key.as_deref()?;
+        Some(Key::parse(key).map_or_else(|| key.trim().to_string(), |key| key.to_string()))
+    }
+}
+
+/// Checks the number of songs a page was asked to have.
+pub fn page_size(first: Option<i32>) -> ChordmateResult<Option<i64>> {
+    match first {
+        Some(first) if first < 0 => Err(validation::invalid(
+            "first",
+            "`first` must not be negative.",
+        )),
+        _ => Ok(first.map(i64::from)),
+    }
+}
+
+/// Cursors point at a song by its position in a list of songs.
+pub fn encode_cursor(offset: i64) -> String {
+    format!("song:{offset}")
+}
+
+/// The offset of the song after the one `cursor` points at.
+pub fn offset_after(cursor: Option<&str>) -> ChordmateResult<i64> {
+    let Some(cursor) = cursor else {
+        return Ok(0);
+    };
+    cursor
+        .strip_prefix("song:")
+        .and_then(|offset| offset.parse::<i64>().ok())
+        .filter(|&offset| offset >= 0)
+        .and_then(|offset| offset.checked_add(1))
+        .ok_or_else(|| validation::invalid("after", format!("'{cursor}' is not a valid cursor.")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ChordmateError;
+
+    fn invalid_field(result: ChordmateResult<impl std::fmt::Debug>) -> String {
+        match result {
+            Err(ChordmateError::Validation(violations)) => violations[0].0.clone(),
+            other => panic!("{other:?} is not a validation error"),
+        }
+    }
+
+    #[test]
+    fn cursors_point_at_the_song_before_the_next_page() {
+        assert_eq!(offset_after(None).unwrap(), 0);
+        for offset in [0, 1, 49, i64::MAX - 1] {
+            assert_eq!(
+                offset_after(Some(&encode_cursor(offset))).unwrap(),
+                offset + 1
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_cursors_are_rejected() {
+        for cursor in [
+            "",
+            "song:",
+            "song:x",
+            "song:-1",
+            "setlist:3",
+            "3",
+            "song:9223372036854775807",
+        ] {
+            assert_eq!(
+                invalid_field(offset_after(Some(cursor))),
+                "after",
+                "{cursor}"
+            );
+        }
+    }
+
+    #[test]
+    fn pages_must_not_have_a_negative_size() {
+        assert_eq!(page_size(None).unwrap(), None);
+        assert_eq!(page_size(Some(0)).unwrap(), Some(0));
+        assert_eq!(
+            page_size(Some(i32::MAX)).unwrap(),
+            Some(i64::from(i32::MAX))
+        );
+        assert_eq!(invalid_field(page_size(Some(-1))), "first");
+    }
+
+    #[test]
+    fn searches_find_every_word_by_its_beginning() {
+        assert_eq!(
+            search_words("Don't Stop Me-Now!"),
+            ["don", "t", "stop", "me", "now"]
+        );
+        assert_eq!(to_tsquery("Fly me to"), Some("fly:* & me:* & to:*".into()));
+        assert_eq!(to_tsquery(" -- "), None);
+    }
+
+    #[test]
+    fn keys_are_matched_the_way_they_are_stored() {
+        let filter = |key: &str| SongFilter {
+            key: Some(key.to_string()),
+            ..SongFilter::default()
+        };
+        assert_eq!(filter("Amin").normalized_key(), Some("Am".into()));
+        assert_eq!(filter(" H ").normalized_key(), Some("H".into()));
+        assert_eq!(SongFilter::default().normalized_key(), None);
+    }
+}
@@ -0,0 +1,311 @@
+//! Printable PDF songbooks with the chords positioned above the lyrics.
+
+use crate::chord_diagram::{Canvas, ChordDiagram, Instrument, DIAGRAM_HEIGHT};
+use crate::pdf::{Font, Page, PdfDocument, A4_HEIGHT, A4_WIDTH};
+use crate::song::Song;
+use crate::song_content::{Line, ParsedSong};
+
+pub struct SongbookOptions {
+    /// The instrument to print diagrams of the chords of every song for, if any.
+    pub chord_diagrams: Option<Instrument>,
+}
+
+const MARGIN: f32 = 50.0;
+const TITLE_SIZE: f32 = 18.0;
+const ARTIST_SIZE: f32 = 12.0;
+const TEXT_SIZE: f32 = 10.0;
+const LINE_HEIGHT: f32 = 12.0;
+const SECTION_GAP: f32 = 8.0;
+
+struct Layout {
+    document: PdfDocument,
+    y: f32,
+}
+
+impl Layout {
+    fn new_page(&mut self) {
+        self.document.add_page();
+        self.y = A4_HEIGHT - MARGIN;
+    }
+
+    fn page(&mut self) -> &mut Page {
+        let last = self.document.page_count() - 1;
+        self.document.page_mut(last).unwrap()
+    }
+
+    /// Starts a new page unless there is `height` space left on the current one.
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+    }
+
+    fn text(&mut self, font: Font, size: f32, text: &str) {
+        self.y -= size;
+        let y = self.y;
+        self.page().text(MARGIN, y, font, size, text);
+    }
+}
+
+fn max_line_length() -> usize {
+    ((A4_WIDTH - 2.0 * MARGIN) / (TEXT_SIZE * Font::Courier.char_width())) as usize
+}
+
+/// Breaks a line that does not fit the page into several, keeping the chords above the syllables
+/// they belong to.
+fn wrap(line: &Line, width: usize) -> Vec<(String, String)> {
+    let mut chords: Vec<char> = line.chord_line().chars().collect();
+    let mut lyrics: Vec<char> = line.lyrics.chars().collect();
+    let mut wrapped = Vec::new();
+    while chords.len().max(lyrics.len()) > width {
+        let is_blank = |text: &[char], column: usize| text.get(column).is_none_or(|&c| c == ' ');
+        let column = (1..=width)
+            .rev()
+            .find(|&column| {
+                is_blank(&lyrics, column)
+                    && (is_blank(&chords, column) || is_blank(&chords, column - 1))
+            })
+            .unwrap_or(width);
+        let split = |text: &mut Vec<char>| text.split_off(column.min(text.len()));
+        let (rest_chords, rest_lyrics) = (split(&mut chords), split(&mut lyrics));
+        wrapped.push((chords.iter().collect(), lyrics.iter().collect()));
+        let leading = |text: &[char]| text.iter().take_while(|&&c| c == ' ').count();
+        let indent = match (rest_chords.is_empty(), rest_lyrics.is_empty()) {
+            (true, _) => leading(&rest_lyrics),
+            (_, true) => leading(&rest_chords),
+            _ => leading(&rest_chords).min(leading(&rest_lyrics)),
+        };
+        chords = rest_chords.into_iter().skip(indent).collect();
+        lyrics = rest_lyrics.into_iter().skip(indent).collect();
+    }
+    wrapped.push((chords.into_iter().collect(), lyrics.into_iter().collect()));
+    wrapped
+}
+
+/// Draws chord diagrams onto a page with their top left corner at `left`, `top`.
+struct PageCanvas<'a> {
+    page: &'a mut Page,
+    left: f32,
+    top: f32,
+}
+
+impl PageCanvas<'_> {
+    fn point(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (self.left + x, self.top - y)
+    }
+}
+
+impl Canvas for PageCanvas<'_> {
+    fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32) {
+        let (from, to) = (self.point(from), self.point(to));
+        self.page.line(from, to, width);
+    }
+
+    fn circle(&mut self, center: (f32, f32), radius: f32, filled: bool) {
+        let (x, y) = self.point(center);
+        self.page.circle(x, y, radius, filled);
+    }
+
+    fn rect(&mut self, top_left: (f32, f32), (width, height): (f32, f32), filled: bool) {
+        let (x, y) = self.point(top_left);
+        self.page.rect(x, y - height, width, height, filled);
+    }
+
+    fn text(&mut self, position: (f32, f32), size: f32, bold: bool, text: &str) {
+        let font = if bold {
+            Font::HelveticaBold
+        } else {
+            Font::Helvetica
+        };
+        let width = text.chars().count() as f32 * size * font.char_width();
+        let (x, y) = self.point(position);
+        self.page.text(x - width / 2.0, y, font, size, text);
+    }
+}
+
+fn render_diagrams(layout: &mut Layout, content: &ParsedSong, instrument: Instrument) {
+    let diagrams: Vec<ChordDiagram> = content
+        .distinct_chords()
+        .iter()
+        .filter_map(|chord| ChordDiagram::new(chord, instrument))
+        .collect();
+    let mut rows: Vec<Vec<&ChordDiagram>> = Vec::new();
+    let mut row_width = f32::INFINITY;
+    for diagram in &diagrams {
+        if row_width + diagram.width() > A4_WIDTH - 2.0 * MARGIN {
+            rows.push(Vec::new());
+            row_width = 0.0;
+        }
+        rows.last_mut().unwrap().push(diagram);
+        row_width += diagram.width();
+    }
+    for row in rows {
+        layout.reserve(DIAGRAM_HEIGHT);
+        let top = layout.y;
+        let mut left = MARGIN;
+        for diagram in row {
+            diagram.draw(&mut PageCanvas {
+                page: layout.page(),
+                left,
+                top,
+            });
+            left += diagram.width();
+        }
+        layout.y -= DIAGRAM_HEIGHT;
+    }
+}
+
+fn render_song(layout: &mut Layout, song: &Song, options: &SongbookOptions) {
+    layout.new_page();
+    layout.text(Font::HelveticaBold, TITLE_SIZE, &song.title);
+    if !song.artist.is_empty() {
+        layout.y -= 4.0;
+        layout.text(Font::Helvetica, ARTIST_SIZE, &song.artist);
+    }
+    layout.y -= 2.0 * SECTION_GAP;
+
+    let content = ParsedSong::from_html(song.loaded_content());
+    if let Some(instrument) = options.chord_diagrams {
+        render_diagrams(layout, &content, instrument);
+        layout.y -= SECTION_GAP;
+    }
+    for section in &content.sections {
+        if let Some(label) = &section.label {
+            // Keep the label together with at least the first line of the section.
+            layout.reserve(3.0 * LINE_HEIGHT);
+            layout.text(Font::HelveticaBold, TEXT_SIZE, label);
+            layout.y -= LINE_HEIGHT - TEXT_SIZE;
+        }
+        for line in &section.lines {
+            for (chords, lyrics) in wrap(line, max_line_length()) {
+                let rows = [(Font::CourierBold, chords), (Font::Courier, lyrics)];
+                let rows: Vec<_> = rows.into_iter().filter(|(_, t)| !t.is_empty()).collect();
+                layout.reserve(rows.len() as f32 * LINE_HEIGHT);
+                for (font, text) in rows {
+                    layout.text(font, TEXT_SIZE, &text);
+                    layout.y -= LINE_HEIGHT - TEXT_SIZE;
+                }
+            }
+        }
+        layout.y -= SECTION_GAP;
+    }
+}
+
+/// Renders the songs into a PDF document, each song starting on a new page.
+pub fn render(songs: &[Song], options: &SongbookOptions) -> Vec<u8> {
+    let mut layout = Layout {
+        document: PdfDocument::new(),
+        y: 0.0,
+    };
+    for song in songs {
+        render_song(&mut layout, song, options);
+    }
+    if layout.document.page_count() == 0 {
+        layout.new_page();
+    }
+    let page_count = layout.document.page_count();
+    for i in 0..page_count {
+        let label = format!("{} / {page_count}", i + 1);
+        let width = label.len() as f32 * 9.0 * Font::Helvetica.char_width();
+        let page = layout.document.page_mut(i).unwrap();
+        page.text(
+            (A4_WIDTH - width) / 2.0,
+            MARGIN / 2.0,
+            Font::Helvetica,
+            9.0,
+            &label,
+        );
+    }
+    layout.document.to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::song_content::ChordAnnotation;
+
+    fn song(title: &str, content: String) -> Song {
+        Song {
+            id: 1,
+            title: title.to_string(),
+            artist: String::new(),
+            spotify_track: String::new(),
+            content: Some(content),
+            key: None,
+            bpm: None,
+            time_signature: None,
+            capo: None,
+            duration_seconds: None,
+            owner_id: None,
+        }
+    }
+
+    fn page_count(pdf: &[u8]) -> usize {
+        let pdf = String::from_utf8_lossy(pdf);
+        let count = &pdf[pdf.find("/Count ").unwrap() + "/Count ".len()..];
+        count[..count.find(' ').unwrap()].parse().unwrap()
+    }
+
+    #[test]
+    fn wrapped_lines_keep_their_chords_above_the_lyrics() {
+        let line = Line {
+            chords: vec![
+                ChordAnnotation {
+                    offset: 4,
+                    symbol: String::from("G"),
+                },
+                ChordAnnotation {
+                    offset: 35,
+                    symbol: String::from("D7"),
+                },
+            ],
+            lyrics: String::from("The quick brown fox jumps over the lazy dog"),
+        };
+        let wrapped = wrap(&line, 20);
+        assert_eq!(wrapped.len(), 3);
+        assert!(wrapped.iter().all(|(chords, lyrics)| {
+            chords.chars().count() <= 20 && lyrics.chars().count() <= 20
+        }));
+        let column = |text: &str, word: &str| text.find(word);
+        let (chords, lyrics) = &wrapped[0];
+        assert_eq!(column(chords, "G"), column(lyrics, "quick"));
+        let (chords, lyrics) = wrapped
+            .iter()
+            .find(|(chords, _)| chords.contains("D7"))
+            .unwrap();
+        assert_eq!(column(chords, "D7"), column(lyrics, "lazy"));
+    }
+
+    #[test]
+    fn lines_that_fit_are_not_wrapped() {
+        let line = ParsedSong::from_html("<p>Am</p><p>Short</p>").sections[0].lines[0].clone();
+        assert_eq!(
+            wrap(&line, 20),
+            [(String::from("Am"), String::from("Short"))]
+        );
+    }
+
+    #[test]
+    fn every_song_starts_on_a_new_page() {
+        let options = SongbookOptions {
+            chord_diagrams: None,
+        };
+        let songs = [
+            song("One", String::from("<p>C</p><p>La</p>")),
+            song("Two", String::from("<p>G</p><p>La</p>")),
+        ];
+        assert_eq!(page_count(&render(&songs, &options)), 2);
+        assert_eq!(page_count(&render(&[], &options)), 1);
+    }
+
+    #[test]
+    fn long_songs_continue_on_the_next_pages() {
+        let content = "<p>C&nbsp;&nbsp; G</p><p>A line of the song</p>".repeat(70);
+        let options = SongbookOptions {
+            chord_diagrams: Some(Instrument::Guitar),
+        };
+        let pdf = render(&[song("Long", content)], &options);
+        assert_eq!(page_count(&pdf), 3);
+        assert!(String::from_utf8_lossy(&pdf).contains("(3 / 3) Tj"));
+    }
+}
@@ -1,5 +1,5 @@
+use crate::error::{ChordmateError, ChordmateResult};
 use crate::spotify::TokenError::Missing;
-use juniper::{graphql_value, FieldError, FieldResult};
 use log::info;
 use reqwest::Client;
 use serde::Deserialize;
@@ -12,17 +12,13 @@ use tokio::sync::Mutex;
 struct TokenResponse {
     access_token: String,
     refresh_token: String,
-    token_type: String,
     expires_in: u64,
-    scope: String,
 }
 
 struct StoredToken {
     access_token: String,
     refresh_token: String,
-    token_type: String,
     expires_at: Instant,
-    scope: String,
 }
 pub struct SpotifyClient {
     token_cache: Mutex<Option<StoredToken>>,
@@ -43,34 +39,27 @@ pub enum TokenError {
     FailedToGet(String),
 }
 
-impl From<TokenError> for FieldError {
-    fn from(err: TokenError) -> Self {
-        match err {
-            TokenError::Missing => FieldError::new(
-                "User is not authenticated",
-                graphql_value!({"code": "UNAUTHENTICATED"}),
-            ),
-            TokenError::FailedToGet(message) => FieldError::new(
-                format!("Failed to get token: {message}"),
-                graphql_value!({"code": "TOKEN_ERROR"}),
-            ),
-        }
-    }
-}
-
 impl SpotifyClient {
-    pub fn new() -> Self {
+    pub fn new(client_id: String, client_secret: String, redirect_uri: String) -> Self {
         let client = SpotifyClient {
             token_cache: Mutex::new(None),
-            client_id: env::var("SPOTIFY_CLIENT_ID").expect("SPOTIFY_CLIENT_ID is not set."),
-            client_secret: env::var("SPOTIFY_CLIENT_SECRET")
-                .expect("SPOTIFY_CLIENT_ID is not set."),
-            redirect_uri: env::var("SPOTIFY_REDIRECT_URI").expect("SPOTIFY_CLIENT_ID is not set."),
+            client_id,
+            client_secret,
+            redirect_uri,
         };
         info!("Spotify: Create new client with id ${}", client.client_id);
         client
     }
 
+    /// A client for the app registered with Spotify in the `SPOTIFY_*` environment variables.
+    pub fn from_env() -> Self {
+        SpotifyClient::new(
+            env::var("SPOTIFY_CLIENT_ID").expect("SPOTIFY_CLIENT_ID is not set."),
+            env::var("SPOTIFY_CLIENT_SECRET").expect("SPOTIFY_CLIENT_ID is not set."),
+            env::var("SPOTIFY_REDIRECT_URI").expect("SPOTIFY_CLIENT_ID is not set."),
+        )
+    }
+
     pub fn client_id(&self) -> &str {
         self.client_id.as_ref()
     }
@@ -114,27 +103,20 @@ impl SpotifyClient {
         }
 
         let resp: TokenResponse = serde_json::from_str(&text).map_err(|e| {
-            TokenError::FailedToGet(format!(
-                "Failed to parse {} into TokenResponse: {}",
-                text,
-                e.to_string()
-            ))
+            TokenError::FailedToGet(format!("Failed to parse {text} into TokenResponse: {e}"))
         })?;
         let mut guard = self.token_cache.lock().await;
         info!("got access token: {}", resp.access_token);
         info!("got refresh token: {}", resp.refresh_token);
-        info!("token type {}", resp.token_type);
         let expires_at = Instant::now() + Duration::from_secs(resp.expires_in);
         info!(
             "expires in {}s (at {:?}) (minus margin)",
             resp.expires_in, expires_at
         );
         *guard = Some(StoredToken {
-            token_type: resp.token_type,
             access_token: resp.access_token,
             refresh_token: resp.refresh_token,
             expires_at: expires_at - Duration::from_secs(60),
-            scope: resp.scope,
         });
 
         Ok(true)
@@ -201,7 +183,7 @@ impl SpotifyClient {
         }
     }
 
-    pub async fn search_tracks(&self, query: &str) -> FieldResult<Value> {
+    pub async fn search_tracks(&self, query: &str) -> ChordmateResult<Value> {
         info!("Spotify: search tracks '{}'", query);
         let token = self.access_token().await?;
         let client = Client::new();
@@ -212,21 +194,20 @@ impl SpotifyClient {
             .send()
             .await
             .map_err(|e| {
-                FieldError::new(e.to_string(), graphql_value!({"message": e.to_string()}))
+                ChordmateError::SpotifyRequestFailed(format!("Spotify could not be reached: {e}"))
             })?;
 
         if !res.status().is_success() {
-            return Err(FieldError::new(
-                format!(
-                    "Spotify returned {}: {}",
-                    res.status(),
-                    res.text().await.unwrap_or_default(),
-                ),
-                graphql_value!({}),
-            ));
+            return Err(ChordmateError::SpotifyRequestFailed(format!(
+                "Spotify returned {}: {}",
+                res.status(),
+                res.text().await.unwrap_or_default(),
+            )));
         }
 
-        Ok(res.json().await?)
+        res.json().await.map_err(|e| {
+            ChordmateError::SpotifyRequestFailed(format!("Spotify sent an invalid answer: {e}"))
+        })
     }
 
     pub async fn access_token_expires_in(&self) -> Duration {
@@ -0,0 +1,68 @@
+//! Tags to group songs by, e.g. the occasions they are played at.
+
+use crate::error::ChordmateResult;
+use crate::validation;
+use juniper::GraphQLObject;
+use tokio_postgres::{Error, Row};
+
+/// Tags longer than this are most likely something pasted by accident.
+const MAX_TAG_LENGTH: usize = 50;
+
+#[derive(GraphQLObject, Clone, Debug, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    /// How many of the songs the calling user may see have this tag.
+    pub song_count: i32,
+}
+
+impl Tag {
+    pub fn from_row(row: &Row) -> Result<Tag, Error> {
+        let song_count: i64 = row.try_get("song_count")?;
+        Ok(Tag {
+            name: row.try_get("name")?,
+            song_count: song_count as i32,
+        })
+    }
+}
+
+/// Trims a tag name and checks that it can be used.
+pub fn normalize_name(name: &str) -> ChordmateResult<&str> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_TAG_LENGTH {
+        return Err(validation::invalid(
+            "name",
+            format!("A tag must have between 1 and {MAX_TAG_LENGTH} characters."),
+        ));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ChordmateError;
+
+    fn is_rejected(name: &str) -> bool {
+        matches!(normalize_name(name), Err(ChordmateError::Validation(_)))
+    }
+
+    #[test]
+    fn names_are_trimmed_and_keep_their_case() {
+        assert_eq!(normalize_name("  Wedding ").unwrap(), "Wedding");
+        assert_eq!(normalize_name("Open Mic").unwrap(), "Open Mic");
+        assert_eq!(normalize_name("jazz").unwrap(), "jazz");
+    }
+
+    #[test]
+    fn names_must_not_be_empty() {
+        assert!(is_rejected(""));
+        assert!(is_rejected(" \t\n"));
+    }
+
+    #[test]
+    fn names_are_limited_in_characters() {
+        let longest = "ä".repeat(MAX_TAG_LENGTH);
+        assert_eq!(normalize_name(&format!(" {longest} ")).unwrap(), longest);
+        assert!(is_rejected(&format!("{longest}a")));
+    }
+}
@@ -0,0 +1,196 @@
+//! Checking the input of mutations, reporting every problem with it at once.
+//!
+//! Invalid input is rejected with a `VALIDATION_ERROR` that lists the problems per field, e.g.
+//! `{"code": "VALIDATION_ERROR", "fields": [{"field": "title", "message": "..."}]}`.
+
+use crate::error::{ChordmateError, ChordmateResult};
+use crate::html;
+use crate::song_content;
+
+/// For titles, artists, setlist names and the like.
+pub const MAX_NAME_LENGTH: usize = 200;
+/// Far longer than any song, but short enough to keep a single request from filling the database.
+pub const MAX_CONTENT_LENGTH: usize = 100_000;
+
+#[derive(Debug, Default)]
+pub struct Validator {
+    violations: Vec<(String, String)>,
+}
+
+impl Validator {
+    pub fn new() -> Validator {
+        Validator::default()
+    }
+
+    pub fn invalid(&mut self, field: &str, message: impl Into<String>) {
+        self.violations.push((field.to_string(), message.into()));
+    }
+
+    /// Takes the value of a check that either produces a value or explains what is wrong.
+    pub fn value<T>(&mut self, field: &str, result: Result<T, String>) -> Option<T> {
+        result.map_err(|message| self.invalid(field, message)).ok()
+    }
+
+    pub fn max_length(&mut self, field: &str, value: &str, max: usize) {
+        if value.chars().count() > max {
+            self.invalid(
+                field,
+                format!("`{field}` must not be longer than {max} characters."),
+            );
+        }
+    }
+
+    pub fn not_blank(&mut self, field: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.invalid(field, format!("`{field}` must not be empty."));
+        }
+    }
+
+    pub fn range(&mut self, field: &str, value: i32, min: i32, max: i32) {
+        self.value(field, in_range(field, value, min, max));
+    }
+
+    /// Song content has to be well-formed HTML of limited size whose chord lines only use chords
+    /// that can be transposed and shown as diagrams.
+    pub fn content(&mut self, field: &str, content: &str) {
+        if content.chars().count() > MAX_CONTENT_LENGTH {
+            self.max_length(field, content, MAX_CONTENT_LENGTH);
+            return;
+        }
+        if let Err(message) = html::check_well_formed(content) {
+            self.invalid(field, format!("The content is not well-formed: {message}"));
+        }
+        for (i, line) in html::text_lines(content).iter().enumerate() {
+            for symbol in song_content::unknown_chord_symbols(&line.text) {
+                self.invalid(field, format!("Line {}: '{symbol}' is not a chord.", i + 1));
+            }
+        }
+    }
+
+    pub fn finish(self) -> ChordmateResult<()> {
+        if self.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ChordmateError::Validation(self.violations))
+        }
+    }
+}
+
+/// Checks that a number is between `min` and `max`, for use with [`Validator::value`].
+pub fn in_range(field: &str, value: i32, min: i32, max: i32) -> Result<i32, String> {
+    if (min..=max).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("`{field}` must be between {min} and {max}."))
+    }
+}
+
+/// The error for a single invalid field.
+pub fn invalid(field: &str, message: impl Into<String>) -> ChordmateError {
+    ChordmateError::Validation(vec![(field.to_string(), message.into())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(validator: Validator) -> Vec<(String, String)> {
+        validator.violations
+    }
+
+    #[test]
+    fn valid_input_passes() {
+        let mut validator = Validator::new();
+        validator.not_blank("title", "Wonderwall");
+        validator.max_length("title", "Wonderwall", 10);
+        validator.range("bpm", 87, 1, 400);
+        validator.content("content", "<p>G  D  Em</p><p>Today is gonna be the day</p>");
+        assert_eq!(validator.value("key", Ok::<_, String>(3)), Some(3));
+        assert!(validator.finish().is_ok());
+    }
+
+    #[test]
+    fn every_check_reports_its_field() {
+        let mut validator = Validator::new();
+        validator.not_blank("title", "  ");
+        validator.max_length("artist", "Oasis", 4);
+        validator.range("capo", 12, 0, 11);
+        assert_eq!(
+            validator.value::<i32>("key", Err("'H#' is not a key.".into())),
+            None
+        );
+        assert_eq!(
+            messages(validator),
+            [
+                ("title".into(), "`title` must not be empty.".into()),
+                (
+                    "artist".into(),
+                    "`artist` must not be longer than 4 characters.".into()
+                ),
+                ("capo".into(), "`capo` must be between 0 and 11.".into()),
+                ("key".into(), "'H#' is not a key.".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lengths_are_counted_in_characters() {
+        let mut validator = Validator::new();
+        validator.max_length("title", "Déjà vu", 7);
+        assert!(validator.finish().is_ok());
+    }
+
+    #[test]
+    fn content_must_be_well_formed_and_use_known_chords() {
+        let mut validator = Validator::new();
+        validator.content("content", "<p>G  C  Hm7  D</p><p>Verse");
+        let violations = messages(validator);
+        assert_eq!(violations.len(), 2);
+        assert!(violations[0]
+            .1
+            .starts_with("The content is not well-formed"));
+        assert_eq!(violations[1].1, "Line 1: 'Hm7' is not a chord.");
+
+        let mut validator = Validator::new();
+        validator.content("content", &"a".repeat(MAX_CONTENT_LENGTH + 1));
+        assert_eq!(
+            messages(validator),
+            [(
+                "content".into(),
+                format!("`content` must not be longer than {MAX_CONTENT_LENGTH} characters.")
+            )]
+        );
+    }
+
+    #[test]
+    fn the_error_lists_every_field() {
+        let mut validator = Validator::new();
+        validator.not_blank("title", "");
+        validator.range("bpm", 0, 1, 400);
+        assert_eq!(
+            validator.finish(),
+            Err(ChordmateError::Validation(vec![
+                ("title".into(), "`title` must not be empty.".into()),
+                ("bpm".into(), "`bpm` must be between 1 and 400.".into()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn a_single_invalid_field_is_a_validation_error() {
+        assert_eq!(
+            invalid("after", "'x' is not a valid cursor."),
+            ChordmateError::Validation(vec![("after".into(), "'x' is not a valid cursor.".into())])
+        );
+    }
+
+    #[test]
+    fn ranges_include_their_bounds() {
+        assert_eq!(in_range("capo", 0, 0, 11), Ok(0));
+        assert_eq!(in_range("capo", 11, 0, 11), Ok(11));
+        assert_eq!(
+            in_range("capo", -1, 0, 11),
+            Err("`capo` must be between 0 and 11.".to_string())
+        );
+    }
+}
@@ -0,0 +1,542 @@
+//! Runs the GraphQL schema against songs kept in memory, without a database.
+
+use chordmate::auth::{Context, User};
+use chordmate::database_connection::DatabaseConnection;
+use chordmate::ql_mutation::QLMutation;
+use chordmate::ql_query::QLQuery;
+use chordmate::ql_subscription::QLSubscription;
+use chordmate::song_events::SongEvents;
+use chordmate::song_repository::InMemorySongRepository;
+use chordmate::spotify::SpotifyClient;
+use futures::{Stream, StreamExt};
+use juniper::{RootNode, Variables};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+type Schema = RootNode<QLQuery, QLMutation, QLSubscription>;
+
+struct App {
+    schema: Schema,
+    songs: Arc<InMemorySongRepository>,
+}
+
+impl App {
+    fn new() -> App {
+        let spotify_client = SpotifyClient::new(
+            String::from("unused"),
+            String::from("unused"),
+            String::from("unused"),
+        );
+        let song_events = SongEvents::new();
+        let schema = Schema::new(
+            QLQuery {
+                database_connection: DatabaseConnection::disconnected(),
+                spotify_client: Arc::new(spotify_client),
+            },
+            QLMutation {
+                database_connection: DatabaseConnection::disconnected(),
+                song_events: song_events.clone(),
+            },
+            QLSubscription { song_events },
+        );
+        let songs = InMemorySongRepository::new();
+        for id in 1..=3 {
+            songs.add_user(user(id));
+        }
+        App {
+            schema,
+            songs: Arc::new(songs),
+        }
+    }
+
+    /// The context of requests by the user with this id, or of anonymous ones.
+    fn context(&self, user_id: Option<i32>) -> Context {
+        Context {
+            user: user_id.map(user),
+            ..Context::anonymous(DatabaseConnection::disconnected(), self.songs.clone())
+        }
+    }
+
+    /// Runs a query as the user with this id, or anonymously, and returns the JSON response.
+    async fn run(&self, user_id: Option<i32>, query: &str) -> Value {
+        let context = self.context(user_id);
+        let (data, errors) =
+            juniper::execute(query, None, &self.schema, &Variables::new(), &context)
+                .await
+                .unwrap();
+        json!({"data": data, "errors": errors})
+    }
+}
+
+/// The users 1 to 3 are known to every app, as `user1` to `user3`.
+fn user(id: i32) -> User {
+    User {
+        id,
+        username: format!("user{id}"),
+    }
+}
+
+/// The events of a subscription with a single field, as JSON.
+async fn subscribe<'a>(
+    app: &'a App,
+    context: &'a Context,
+    query: &'a str,
+) -> impl Stream<Item = Value> + 'a {
+    let (value, errors) =
+        juniper::resolve_into_stream(query, None, &app.schema, &Variables::new(), context)
+            .await
+            .unwrap();
+    assert!(errors.is_empty());
+    let Some((_, juniper::Value::Scalar(stream))) = value.into_object().unwrap().into_iter().next()
+    else {
+        panic!("{query} did not return a stream");
+    };
+    stream.map(|event| serde_json::to_value(event.unwrap()).unwrap())
+}
+
+fn error_code(response: &Value) -> &str {
+    response["errors"][0]["extensions"]["code"]
+        .as_str()
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn songs_can_be_created_edited_and_found() {
+    let app = App::new();
+    let response = app.run(Some(1), "mutation { addSong }").await;
+    let id = response["data"]["addSong"].as_i64().unwrap();
+
+    let response = app
+        .run(
+            Some(1),
+            &format!(
+                r#"mutation {{
+                    updateSong(id: {id}, input: {{title: "Autumn Leaves", artist: "Kosma", key: "Gm"}})
+                    updateSongContent(id: {id}, content: "<p>Cm7   F7</p><p>The falling leaves</p>")
+                }}"#
+            ),
+        )
+        .await;
+    assert_eq!(response["errors"], json!([]));
+
+    let response = app
+        .run(
+            Some(1),
+            &format!("{{ song(id: {id}) {{ title key chords {{ symbol }} role }} }}"),
+        )
+        .await;
+    assert_eq!(
+        response["data"]["song"],
+        json!({
+            "title": "Autumn Leaves",
+            "key": "Gm",
+            "chords": [{"symbol": "Cm7"}, {"symbol": "F7"}],
+            "role": "OWNER",
+        })
+    );
+
+    let response = app
+        .run(
+            Some(1),
+            r#"{
+                songs(search: "fall") { title }
+                songsConnection(key: "Gmin") { totalCount edges { node { content chords { symbol } } } }
+            }"#,
+        )
+        .await;
+    assert_eq!(
+        response["data"]["songs"],
+        json!([{"title": "Autumn Leaves"}])
+    );
+    let connection = &response["data"]["songsConnection"];
+    assert_eq!(connection["totalCount"], json!(1));
+    // Lists leave the content out until it is asked for.
+    assert_eq!(
+        connection["edges"][0]["node"],
+        json!({
+            "content": "<p>Cm7&nbsp;&nbsp;&nbsp;F7</p><p>The falling leaves</p>",
+            "chords": [{"symbol": "Cm7"}, {"symbol": "F7"}],
+        })
+    );
+}
+
+#[tokio::test]
+async fn songs_are_only_visible_to_their_owner() {
+    let app = App::new();
+    let response = app.run(Some(1), "mutation { addSong }").await;
+    let id = response["data"]["addSong"].as_i64().unwrap();
+
+    let response = app.run(Some(2), "{ songs { id } }").await;
+    assert_eq!(response["data"]["songs"], json!([]));
+    let response = app
+        .run(Some(2), &format!("{{ song(id: {id}) {{ id }} }}"))
+        .await;
+    assert_eq!(error_code(&response), "FORBIDDEN");
+    let response = app
+        .run(None, &format!("{{ song(id: {id}) {{ id }} }}"))
+        .await;
+    assert_eq!(error_code(&response), "UNAUTHENTICATED");
+}
+
+#[tokio::test]
+async fn deleted_songs_are_not_found() {
+    let app = App::new();
+    let response = app.run(Some(1), "mutation { addSong }").await;
+    let id = response["data"]["addSong"].as_i64().unwrap();
+
+    let response = app
+        .run(Some(1), &format!("mutation {{ deleteSong(id: {id}) }}"))
+        .await;
+    assert_eq!(response["data"]["deleteSong"], json!(true));
+    let response = app
+        .run(Some(1), &format!("{{ song(id: {id}) {{ id }} }}"))
+        .await;
+    assert_eq!(error_code(&response), "NOT_FOUND");
+}
+
+#[tokio::test]
+async fn invalid_input_is_rejected() {
+    let app = App::new();
+    let response = app.run(Some(1), "mutation { addSong }").await;
+    let id = response["data"]["addSong"].as_i64().unwrap();
+
+    let response = app
+        .run(
+            Some(1),
+            &format!(r#"mutation {{ updateSong(id: {id}, input: {{title: " ", bpm: 0}}) }}"#),
+        )
+        .await;
+    assert_eq!(error_code(&response), "VALIDATION_ERROR");
+    let fields: Vec<&str> = response["errors"][0]["extensions"]["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|field| field["field"].as_str())
+        .collect();
+    assert_eq!(fields, ["title", "bpm"]);
+}
+
+#[tokio::test]
+async fn transposing_moves_the_key_and_keeps_the_markup() {
+    let app = App::new();
+    let response = app.run(Some(1), "mutation { addSong }").await;
+    let id = response["data"]["addSong"].as_i64().unwrap();
+    app.run(
+        Some(1),
+        &format!(
+            r#"mutation {{
+                updateSong(id: {id}, input: {{key: "G"}})
+                updateSongContent(id: {id}, content: "<h1>Intro</h1><p>G&nbsp; D</p><p><em>Hum</em></p>")
+            }}"#
+        ),
+    )
+    .await;
+
+    let response = app
+        .run(
+            Some(1),
+            &format!("mutation {{ transposeSong(id: {id}, semitones: 2) }}"),
+        )
+        .await;
+    assert_eq!(response["errors"], json!([]));
+    let response = app
+        .run(Some(1), &format!("{{ song(id: {id}) {{ key content }} }}"))
+        .await;
+    assert_eq!(
+        response["data"]["song"],
+        json!({"key": "A", "content": "<h1>Intro</h1><p>A&nbsp;&nbsp;E</p><p><em>Hum</em></p>"})
+    );
+}
+
+#[tokio::test]
+async fn subscribers_only_hear_about_songs_they_may_see() {
+    let app = App::new();
+    let context = app.context(Some(2));
+    let events = subscribe(&app, &context, "subscription { songsChanged { id kind } }").await;
+    tokio::pin!(events);
+
+    app.run(Some(1), "mutation { addSong }").await;
+    let response = app.run(Some(2), "mutation { addSong }").await;
+    let id = response["data"]["addSong"].as_i64().unwrap();
+    assert_eq!(
+        events.next().await,
+        Some(json!({"id": id, "kind": "CREATED"}))
+    );
+}
+
+#[tokio::test]
+async fn song_subscriptions_end_when_the_song_is_deleted() {
+    let app = App::new();
+    let response = app.run(Some(1), "mutation { addSong }").await;
+    let id = response["data"]["addSong"].as_i64().unwrap();
+    let context = app.context(Some(1));
+    let query = format!("subscription {{ songChanged(id: {id}) {{ title }} }}");
+    let events = subscribe(&app, &context, &query).await;
+    tokio::pin!(events);
+
+    app.run(
+        Some(1),
+        &format!(r#"mutation {{ updateSong(id: {id}, input: {{title: "Renamed"}}) }}"#),
+    )
+    .await;
+    assert_eq!(events.next().await, Some(json!({"title": "Renamed"})));
+    app.run(Some(1), &format!("mutation {{ deleteSong(id: {id}) }}"))
+        .await;
+    assert_eq!(events.next().await, None);
+}
+
+#[tokio::test]
+async fn earlier_revisions_can_be_compared_and_restored() {
+    let app = App::new();
+    let response = app.run(Some(1), "mutation { addSong }").await;
+    let id = response["data"]["addSong"].as_i64().unwrap();
+    for content in ["<p>G</p><p>Hello</p>", "<p>D</p><p>Hello</p>"] {
+        app.run(
+            Some(1),
+            &format!(r#"mutation {{ updateSongContent(id: {id}, content: "{content}") }}"#),
+        )
+        .await;
+    }
+
+    let response = app
+        .run(
+            Some(1),
+            &format!("{{ song(id: {id}) {{ revisions {{ id content createdAt }} }} }}"),
+        )
+        .await;
+    let revisions = response["data"]["song"]["revisions"].as_array().unwrap();
+    assert_eq!(revisions.len(), 2);
+    assert_eq!(revisions[0]["content"], json!("<p>D</p><p>Hello</p>"));
+    let created_at = revisions[0]["createdAt"].as_str().unwrap();
+    assert!(
+        created_at.len() == 20 && created_at.ends_with('Z'),
+        "{created_at}"
+    );
+    let first = revisions[1]["id"].as_i64().unwrap();
+
+    let response = app
+        .run(
+            Some(1),
+            &format!("{{ song(id: {id}) {{ diff(fromRevision: {first}) {{ kind }} }} }}"),
+        )
+        .await;
+    assert_eq!(
+        response["data"]["song"]["diff"],
+        json!([{"kind": "CHORDS_CHANGED"}])
+    );
+
+    let response = app
+        .run(
+            Some(1),
+            &format!("mutation {{ restoreSongRevision(songId: {id}, revisionId: {first}) }}"),
+        )
+        .await;
+    assert_eq!(response["errors"], json!([]));
+    let response = app
+        .run(
+            Some(1),
+            &format!("{{ song(id: {id}) {{ content revisions {{ id }} }} }}"),
+        )
+        .await;
+    assert_eq!(
+        response["data"]["song"]["content"],
+        json!("<p>G</p><p>Hello</p>")
+    );
+    assert_eq!(
+        response["data"]["song"]["revisions"]
+            .as_array()
+            .unwrap()
+            .len(),
+        3
+    );
+
+    let response = app
+        .run(
+            Some(1),
+            &format!("mutation {{ restoreSongRevision(songId: {id}, revisionId: 999) }}"),
+        )
+        .await;
+    assert_eq!(error_code(&response), "NOT_FOUND");
+}
+
+#[tokio::test]
+async fn songs_can_be_found_by_tag() {
+    let app = App::new();
+    let mut ids = Vec::new();
+    for name in ["Wedding", " wedding "] {
+        let response = app.run(Some(1), "mutation { addSong }").await;
+        let id = response["data"]["addSong"].as_i64().unwrap();
+        app.run(
+            Some(1),
+            &format!(r#"mutation {{ addTag(songId: {id}, name: "{name}") }}"#),
+        )
+        .await;
+        ids.push(id);
+    }
+    app.run(
+        Some(1),
+        &format!(
+            r#"mutation {{ addTag(songId: {}, name: "Christmas") }}"#,
+            ids[1]
+        ),
+    )
+    .await;
+
+    let response = app
+        .run(Some(1), r#"{ songs(tag: "WEDDING") { id } }"#)
+        .await;
+    assert_eq!(
+        response["data"]["songs"],
+        json!([{"id": ids[0]}, {"id": ids[1]}])
+    );
+    let response = app.run(Some(1), "{ tags { name songCount } }").await;
+    assert_eq!(
+        response["data"]["tags"],
+        json!([
+            {"name": "Wedding", "songCount": 2},
+            {"name": "Christmas", "songCount": 1},
+        ])
+    );
+    let response = app
+        .run(Some(1), &format!("{{ song(id: {}) {{ tags }} }}", ids[1]))
+        .await;
+    assert_eq!(
+        response["data"]["song"]["tags"],
+        json!(["Christmas", "Wedding"])
+    );
+
+    let response = app
+        .run(
+            Some(1),
+            &format!(
+                r#"mutation {{ removeTag(songId: {}, name: "wedding") }}"#,
+                ids[0]
+            ),
+        )
+        .await;
+    assert_eq!(response["data"]["removeTag"], json!(true));
+    let response = app
+        .run(Some(1), r#"{ songs(tag: "Wedding") { id } }"#)
+        .await;
+    assert_eq!(response["data"]["songs"], json!([{"id": ids[1]}]));
+    // Other users do not see the tags of songs they may not see.
+    let response = app.run(Some(2), "{ tags { name } }").await;
+    assert_eq!(response["data"]["tags"], json!([]));
+}
+
+#[tokio::test]
+async fn shared_songs_can_be_used_with_the_shared_role() {
+    let app = App::new();
+    let response = app.run(Some(1), "mutation { addSong }").await;
+    let id = response["data"]["addSong"].as_i64().unwrap();
+    let edit = format!(r#"mutation {{ updateSongContent(id: {id}, content: "<p>Hello</p>") }}"#);
+
+    let response = app
+        .run(
+            Some(1),
+            &format!(r#"mutation {{ shareSong(songId: {id}, username: "user2", role: VIEWER) }}"#),
+        )
+        .await;
+    assert_eq!(response["errors"], json!([]));
+    let response = app
+        .run(
+            Some(2),
+            &format!("{{ song(id: {id}) {{ role owner {{ username }} }} }}"),
+        )
+        .await;
+    assert_eq!(
+        response["data"]["song"],
+        json!({"role": "VIEWER", "owner": {"username": "user1"}})
+    );
+    let response = app.run(Some(2), "{ songs { id } }").await;
+    assert_eq!(response["data"]["songs"], json!([{"id": id}]));
+    assert_eq!(error_code(&app.run(Some(2), &edit).await), "FORBIDDEN");
+    // Only the owner sees who else may use the song.
+    let permissions =
+        format!("{{ song(id: {id}) {{ permissions {{ user {{ username }} role }} }} }}");
+    assert_eq!(
+        error_code(&app.run(Some(2), &permissions).await),
+        "FORBIDDEN"
+    );
+
+    app.run(
+        Some(1),
+        &format!(r#"mutation {{ shareSong(songId: {id}, username: "user2", role: EDITOR) }}"#),
+    )
+    .await;
+    let response = app.run(Some(1), &permissions).await;
+    assert_eq!(
+        response["data"]["song"]["permissions"],
+        json!([{"user": {"username": "user2"}, "role": "EDITOR"}])
+    );
+    assert_eq!(app.run(Some(2), &edit).await["errors"], json!([]));
+    let response = app
+        .run(
+            Some(2),
+            &format!("{{ song(id: {id}) {{ revisions {{ author {{ username }} }} }} }}"),
+        )
+        .await;
+    assert_eq!(
+        response["data"]["song"]["revisions"],
+        json!([{"author": {"username": "user2"}}])
+    );
+
+    let response = app
+        .run(
+            Some(1),
+            &format!(r#"mutation {{ unshareSong(songId: {id}, username: "user2") }}"#),
+        )
+        .await;
+    assert_eq!(response["data"]["unshareSong"], json!(true));
+    let response = app
+        .run(Some(2), &format!("{{ song(id: {id}) {{ id }} }}"))
+        .await;
+    assert_eq!(error_code(&response), "FORBIDDEN");
+    let response = app
+        .run(
+            Some(1),
+            &format!(r#"mutation {{ shareSong(songId: {id}, username: "nobody", role: VIEWER) }}"#),
+        )
+        .await;
+    assert_eq!(error_code(&response), "NOT_FOUND");
+}
+
+#[tokio::test]
+async fn nashville_numbers_need_a_key() {
+    let app = App::new();
+    let response = app.run(Some(1), "mutation { addSong }").await;
+    let id = response["data"]["addSong"].as_i64().unwrap();
+    app.run(
+        Some(1),
+        &format!(r#"mutation {{ updateSongContent(id: {id}, content: "<p>Just words</p>") }}"#),
+    )
+    .await;
+
+    let response = app
+        .run(
+            Some(1),
+            &format!("{{ song(id: {id}) {{ nashvilleContent }} }}"),
+        )
+        .await;
+    assert_eq!(error_code(&response), "VALIDATION_ERROR");
+    assert_eq!(
+        response["errors"][0]["extensions"]["fields"][0]["field"],
+        json!("key")
+    );
+
+    app.run(
+        Some(1),
+        &format!(r#"mutation {{ updateSongContent(id: {id}, content: "<p>Am E7</p><p>La</p>") }}"#),
+    )
+    .await;
+    let response = app
+        .run(
+            Some(1),
+            &format!(r#"{{ song(id: {id}) {{ nashvilleContent(key: "Am") }} }}"#),
+        )
+        .await;
+    assert_eq!(
+        response["data"]["song"]["nashvilleContent"],
+        json!("<p>1m 5⁷</p><p>La</p>")
+    );
+}
@@ -7,10 +7,15 @@ import useSystemTheme from "hooks/useSystemTheme";
 import { BrowserRouter as Router, Routes, Route, Link } from "react-router-dom";
 import About from "components/About";
 import SongDetail from "components/SongDetail";
+import Login from "components/Login";
+import Account from "components/Account";
+import { authLink } from "apollo";
 
-const link = new HttpLink({
-  uri: `http://${window.location.hostname}:3000/graphql`, // your Rust backend
-});
+const link = authLink.concat(
+  new HttpLink({
+    uri: `http://${window.location.hostname}:3000/graphql`, // your Rust backend
+  }),
+);
 
 const client = new ApolloClient({
   link,
@@ -27,12 +32,14 @@ function App() {
       <Router>
         <div className="app-container">
           <nav>
-            <Link to="/">Songs</Link> | <Link to="/about">About</Link>
+            <Link to="/">Songs</Link> | <Link to="/about">About</Link> |{" "}
+            <Account />
           </nav>
           <div className="routes-wrapper">
             <Routes>
               <Route path="/" element={<SongsManager />} />
               <Route path="/about" element={<About />} />
+              <Route path="/login" element={<Login />} />
               <Route path="/songs/:id" element={<SongDetail />} />
             </Routes>
           </div>
@@ -1,8 +1,22 @@
-import { ApolloClient, InMemoryCache, HttpLink } from '@apollo/client';
+import { ApolloClient, ApolloLink, InMemoryCache, HttpLink } from '@apollo/client';
+import { getToken } from './auth';
+
+/** Sends the session token of the logged in user with every request. */
+export const authLink = new ApolloLink((operation, forward) => {
+  const token = getToken();
+  if (token) {
+    operation.setContext(({ headers = {} }: { headers?: Record<string, string> }) => ({
+      headers: { ...headers, authorization: `Bearer ${token}` },
+    }));
+  }
+  return forward(operation);
+});
 
 export const client = new ApolloClient({
-  link: new HttpLink({
-    uri: '/graphql', // served by your Rust backend
-  }),
+  link: authLink.concat(
+    new HttpLink({
+      uri: '/graphql', // served by your Rust backend
+    }),
+  ),
   cache: new InMemoryCache(),
 });
@@ -0,0 +1,14 @@
+const TOKEN_KEY = "chordmate.sessionToken";
+
+/** The bearer token of the session the user logged in with, if any. */
+export function getToken(): string | null {
+  return localStorage.getItem(TOKEN_KEY);
+}
+
+export function setToken(token: string) {
+  localStorage.setItem(TOKEN_KEY, token);
+}
+
+export function clearToken() {
+  localStorage.removeItem(TOKEN_KEY);
+}
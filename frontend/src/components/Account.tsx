@@ -0,0 +1,41 @@
+import { gql } from "@apollo/client";
+import { useApolloClient, useMutation, useQuery } from "@apollo/client/react";
+import { Link } from "react-router-dom";
+import { ME } from "../graphql";
+import { clearToken } from "../auth";
+
+const LOGOUT = gql`
+  mutation Logout {
+    logout
+  }
+`;
+
+interface MeData {
+  me: { id: number; username: string } | null;
+}
+
+/** Shows who is logged in, with a link to log in or a button to log out. */
+export default function Account() {
+  const client = useApolloClient();
+  const { data } = useQuery<MeData>(ME);
+  const [logout] = useMutation(LOGOUT);
+
+  if (!data?.me) {
+    return <Link to="/login">Log in</Link>;
+  }
+
+  const handleLogout = async () => {
+    try {
+      await logout();
+    } finally {
+      clearToken();
+      await client.resetStore();
+    }
+  };
+
+  return (
+    <span>
+      {data.me.username} <button onClick={handleLogout}>Log out</button>
+    </span>
+  );
+}
@@ -0,0 +1,93 @@
+import { useState, FormEvent } from "react";
+import { gql } from "@apollo/client";
+import { useApolloClient, useMutation } from "@apollo/client/react";
+import { useNavigate } from "react-router-dom";
+import { setToken } from "../auth";
+
+const LOGIN = gql`
+  mutation Login($username: String!, $password: String!) {
+    login(username: $username, password: $password)
+  }
+`;
+
+const REGISTER = gql`
+  mutation Register($username: String!, $password: String!) {
+    register(username: $username, password: $password)
+  }
+`;
+
+interface LoginData {
+  login: string;
+}
+
+interface RegisterData {
+  register: string;
+}
+
+interface CredentialsVars {
+  username: string;
+  password: string;
+}
+
+export default function Login() {
+  const [username, setUsername] = useState("");
+  const [password, setPassword] = useState("");
+  const [registering, setRegistering] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+  const client = useApolloClient();
+  const navigate = useNavigate();
+  const [login] = useMutation<LoginData, CredentialsVars>(LOGIN);
+  const [register] = useMutation<RegisterData, CredentialsVars>(REGISTER);
+
+  const handleSubmit = async (event: FormEvent) => {
+    event.preventDefault();
+    setError(null);
+    const variables = { username, password };
+    try {
+      const token = registering
+        ? (await register({ variables })).data?.register
+        : (await login({ variables })).data?.login;
+      if (!token) {
+        return;
+      }
+      setToken(token);
+      // Everything loaded so far was loaded without the session.
+      await client.resetStore();
+      navigate("/");
+    } catch (e) {
+      setError(e instanceof Error ? e.message : String(e));
+    }
+  };
+
+  return (
+    <form onSubmit={handleSubmit}>
+      <h2>{registering ? "Create an account" : "Log in"}</h2>
+      <div>
+        <label>
+          Username{" "}
+          <input
+            value={username}
+            autoComplete="username"
+            onChange={(e) => setUsername(e.target.value)}
+          />
+        </label>
+      </div>
+      <div>
+        <label>
+          Password{" "}
+          <input
+            type="password"
+            value={password}
+            autoComplete={registering ? "new-password" : "current-password"}
+            onChange={(e) => setPassword(e.target.value)}
+          />
+        </label>
+      </div>
+      {error && <p role="alert">{error}</p>}
+      <button type="submit">{registering ? "Create account" : "Log in"}</button>{" "}
+      <button type="button" onClick={() => setRegistering(!registering)}>
+        {registering ? "I already have an account" : "Create an account"}
+      </button>
+    </form>
+  );
+}
@@ -40,10 +40,14 @@ export async function handleError(error: unknown, currentPath: string) {
   }
 
   const errors = (error as { errors: GraphQLError[] }).errors;
-  const unauthenticated = errors.some((e) => {
-    return e.extensions.code === "UNAUTHENTICATED";
+  // Only a missing or broken Spotify login is fixed by logging in to Spotify again.
+  const spotifyLoginNeeded = errors.some((e) => {
+    return (
+      e.extensions.code === "SPOTIFY_NOT_CONNECTED" ||
+      e.extensions.code === "SPOTIFY_TOKEN_ERROR"
+    );
   });
-  if (unauthenticated) {
+  if (spotifyLoginNeeded) {
     startSpotifyOauthFlow(currentPath);
   }
 }
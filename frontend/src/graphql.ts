@@ -10,3 +10,12 @@ export const GET_SONGS = gql`
     }
   }
 `;
+
+export const ME = gql`
+  query Me {
+    me {
+      id
+      username
+    }
+  }
+`;
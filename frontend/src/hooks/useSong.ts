@@ -29,34 +29,22 @@ const UPDATE_SONG_CONTENT = gql`
   }
 `;
 
-interface UpdateSongTrackData {
-  updateSongTrack: boolean;
+interface UpdateSongData {
+  updateSong: number;
 }
 
-interface UpdateSongTrackVars {
+interface UpdateSongVars {
   id: number;
-  track: string;
-}
-
-const UPDATE_SONG_TRACK = gql`
-  mutation UpdateSongContent($id: Int!, $track: String!) {
-    updateSongTrack(id: $id, track: $track)
-  }
-`;
-
-interface UpdateSongMetaData {
-  updateSongMeta: boolean;
-}
-
-interface UpdateSongMetaVars {
-  id: number;
-  title: string;
-  artist: string;
+  input: {
+    title?: string;
+    artist?: string;
+    spotifyTrack?: string;
+  };
 }
 
-const UPDATE_SONG_META = gql`
-  mutation UpdateSongContent($id: Int!, $title: String!, $artist: String!) {
-    updateSongMeta(id: $id, title: $title, artist: $artist)
+const UPDATE_SONG = gql`
+  mutation UpdateSong($id: Int!, $input: SongInput!) {
+    updateSong(id: $id, input: $input)
   }
 `;
 
@@ -70,13 +58,8 @@ export default function useSong(id: number) {
     UpdateSongContentVars
   >(UPDATE_SONG_CONTENT);
 
-  const [updateSongTrack] = useMutation<
-    UpdateSongTrackData,
-    UpdateSongTrackVars
-  >(UPDATE_SONG_TRACK);
-
-  const [updateSongMeta] = useMutation<UpdateSongMetaData, UpdateSongMetaVars>(
-    UPDATE_SONG_META,
+  const [updateSong] = useMutation<UpdateSongData, UpdateSongVars>(
+    UPDATE_SONG,
   );
 
   const saveContent = async (content: string) => {
@@ -89,8 +72,8 @@ export default function useSong(id: number) {
 
   const saveTrack = async (trackId: string) => {
     if (!id) return;
-    await updateSongTrack({
-      variables: { id, track: trackId },
+    await updateSong({
+      variables: { id, input: { spotifyTrack: trackId } },
       refetchQueries: [{ query: GET_SONG, variables: { id } }],
     });
   };
@@ -98,8 +81,8 @@ export default function useSong(id: number) {
   const saveSongMeta = async (title: string, artist: string) => {
     if (!id) return;
     console.log(`save song meta data: '${id}', '${title}', '${artist}'`);
-    await updateSongMeta({
-      variables: { id, title, artist },
+    await updateSong({
+      variables: { id, input: { title, artist } },
       refetchQueries: [{ query: GET_SONG, variables: { id } }],
     });
   };